@@ -0,0 +1,612 @@
+//! Reading and writing the scripts that `pack` and `unpack` work on. Each
+//! command reads either one script, every script in a directory or every file
+//! that matches a pattern like `scripts/**/*.script`, and `-` stands for stdin
+//! or stdout so that the commands can be used in pipelines and git filters.
+
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, Read, Write},
+    panic::{self, AssertUnwindSafe},
+    path::{Component, Path, PathBuf},
+};
+
+use log::{error, info, warn};
+use transcript::{
+    executor::{LimitError, RuntimeError},
+    file::{pack, Glossary, SourceMap},
+    translate::{self, ParseError},
+    utils::{error_token, error_token_with_help, Config, Locale},
+    Error,
+};
+
+/// The path that stands for stdin when reading and stdout when writing
+pub const STDIO: &str = "-";
+
+/// Somewhere a script is read from or written to
+#[derive(Clone)]
+pub enum Stream {
+    Stdin,
+    Stdout,
+    File(PathBuf),
+}
+
+impl Stream {
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Stream::File(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn read(&self) -> Result<String, Error> {
+        let mut contents = String::new();
+
+        let result = match self {
+            Stream::File(path) => fs::read_to_string(path).map(|file| contents = file),
+            _ => io::stdin().read_to_string(&mut contents).map(|_| ()),
+        };
+
+        result.map_err(|error| self.error(error))?;
+        Ok(contents)
+    }
+
+    /// Writes `contents`, creating any directories that the file should be in
+    pub fn write(&self, contents: &str) -> Result<(), Error> {
+        let result = match self {
+            Stream::File(path) => match path.parent() {
+                Some(directory) if !directory.as_os_str().is_empty() => {
+                    fs::create_dir_all(directory).and_then(|_| fs::write(path, contents))
+                }
+                _ => fs::write(path, contents),
+            },
+            _ => io::stdout().write_all(contents.as_bytes()),
+        };
+
+        result.map_err(|error| self.error(error))
+    }
+
+    fn error(&self, error: io::Error) -> Error {
+        Error::File {
+            path: PathBuf::from(self.to_string()),
+            error,
+        }
+    }
+}
+
+impl Display for Stream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stream::Stdin => write!(f, "<stdin>"),
+            Stream::Stdout => write!(f, "<stdout>"),
+            Stream::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// One script to read and where to write what it is turned into
+pub struct Job {
+    pub input: Stream,
+    pub output: Stream,
+}
+
+/// Works out which scripts a command reads and where it writes each of them.
+/// When INPUT is a directory or a pattern, each file is written to the same
+/// place under OUTPUT that it has under the directory (or the part of the
+/// pattern before the first wildcard), with its extension changed from `from`
+/// to `to`. Returns a message explaining how the command was used wrongly, if
+/// it was.
+pub fn jobs(
+    input: &str,
+    output: Option<&str>,
+    in_place: bool,
+    from: &str,
+    to: &str,
+) -> Result<Vec<Job>, String> {
+    if input == STDIO {
+        return match in_place {
+            true => Err(String::from("Cannot change stdin in place")),
+            false => Ok(vec![Job {
+                input: Stream::Stdin,
+                output: stream(output.unwrap_or(STDIO)),
+            }]),
+        };
+    }
+
+    let path = Path::new(input);
+    let (base, inputs) = if path.is_dir() {
+        (path.to_path_buf(), files(path, from))
+    } else if is_pattern(input) {
+        let base = base(path);
+        let pattern = path.strip_prefix(&base).unwrap_or(path);
+        let inputs: Vec<PathBuf> = walk(&base)
+            .into_iter()
+            .filter(|file| matches(pattern, file))
+            .collect();
+
+        if inputs.is_empty() {
+            return Err(format!("No files match '{}'", input));
+        }
+
+        (base, inputs)
+    } else {
+        // Single files are written to stdout when there is no OUTPUT, and
+        // into OUTPUT when it is a directory
+        let output = match output {
+            _ if in_place => Stream::File(path.to_path_buf()),
+            Some(output) if Path::new(output).is_dir() => Stream::File(
+                Path::new(output)
+                    .join(Path::new(path.file_name().unwrap_or_default()).with_extension(to)),
+            ),
+            Some(output) => stream(output),
+            None => Stream::Stdout,
+        };
+
+        return Ok(vec![Job {
+            input: Stream::File(path.to_path_buf()),
+            output,
+        }]);
+    };
+
+    let output = match output {
+        _ if in_place => None,
+        Some(STDIO) => {
+            return Err(format!(
+                "Cannot write {} files to stdout, give a directory to write them to",
+                inputs.len()
+            ))
+        }
+        Some(output) => Some(Path::new(output)),
+        None => {
+            return Err(format!(
+                "Give a directory to write the {} files in '{}' to, or use --in-place",
+                inputs.len(),
+                input
+            ))
+        }
+    };
+
+    Ok(inputs
+        .into_iter()
+        .map(|file| Job {
+            input: Stream::File(base.join(&file)),
+            output: Stream::File(match output {
+                Some(output) => output.join(&file).with_extension(to),
+                None => base.join(&file),
+            }),
+        })
+        .collect())
+}
+
+fn stream(path: &str) -> Stream {
+    match path {
+        STDIO => Stream::Stdout,
+        path => Stream::File(PathBuf::from(path)),
+    }
+}
+
+/// Runs `convert` on every job, reporting the ones that fail rather than
+/// stopping at the first. Returns whether every job succeeded.
+pub fn run(
+    jobs: &[Job],
+    source_map: bool,
+    convert: impl Fn(&Stream, String) -> Result<String, Error>,
+) -> bool {
+    // Scripts that fail to parse are reported below, so that the rest of the
+    // files are still converted
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| ()));
+
+    let mut succeeded = true;
+
+    for job in jobs {
+        let result = job
+            .input
+            .read()
+            .map_err(Failure::Error)
+            .and_then(|contents| {
+                let converted = catch(|| convert(&job.input, contents.clone()))?;
+                job.output.write(&converted).map_err(Failure::Error)?;
+
+                if let (true, Some(input), Some(output)) =
+                    (source_map, job.input.path(), job.output.path())
+                {
+                    write_source_map(input, output, &converted, &contents)
+                        .map_err(Failure::Error)?;
+                }
+
+                Ok(())
+            });
+
+        match result {
+            Ok(()) if jobs.len() > 1 => info!("{} -> {}", job.input, job.output),
+            Ok(()) => (),
+            Err(failure) => {
+                report(&job.input, failure);
+                succeeded = false;
+            }
+        }
+    }
+
+    panic::set_hook(hook);
+    succeeded
+}
+
+/// Packs a script, reading it in the configured locale or the one that it is
+/// detected to be written in
+pub fn pack_script(
+    input: &Stream,
+    contents: String,
+    config: &Config,
+    detect_locale: bool,
+) -> Result<String, Error> {
+    let tokenizer = match input.path() {
+        Some(path) => translate::Tokenizer::from_file(path, contents),
+        None => translate::Tokenizer::from_string(contents),
+    };
+    let mut tokens = tokenizer.tokenize();
+
+    let detected = match detect_locale {
+        true => match Locale::detect(&tokens, config)? {
+            Some(locale) => {
+                info!("{} is written in {}", input, locale.language());
+                Some(locale.apply(config))
+            }
+            None => {
+                warn!("Could not tell which locale {} is written in", input);
+                None
+            }
+        },
+        false => None,
+    };
+    let config = detected.as_ref().unwrap_or(config);
+
+    if let Some(glossary) = Glossary::load(config)? {
+        glossary.normalize(&mut tokens, config)?;
+    }
+
+    Ok(pack(&translate::parse(&mut tokens, config), config))
+}
+
+/// Writes the source map for a file that `pack` or `unpack` generated, next to
+/// the generated file
+fn write_source_map(
+    input: &Path,
+    output: &Path,
+    generated: &str,
+    original: &str,
+) -> Result<(), Error> {
+    // Source maps refer to their original file relative to themselves, so
+    // that both files can be moved together
+    let input = input.canonicalize().map_err(|error| Error::File {
+        path: input.to_path_buf(),
+        error,
+    })?;
+    let directory = output
+        .canonicalize()
+        .ok()
+        .and_then(|output| Some(output.parent()?.to_path_buf()));
+
+    let mut map = SourceMap::between(generated, original);
+    map.file = Some(match (directory, input.file_name()) {
+        (Some(directory), Some(name)) if input.parent() == Some(&directory) => name.into(),
+        _ => input,
+    });
+
+    Stream::File(SourceMap::path_for(output)).write(&map.to_string())
+}
+
+/// Why a script could not be converted
+pub enum Failure {
+    Parse(Box<ParseError>),
+    Error(Error),
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Parse(error) => write!(f, "line {}: {}", error.token.line, error.message),
+            Failure::Error(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// Runs `run`, catching scripts that fail to parse or stop with an error
+pub fn catch<T>(run: impl FnOnce() -> Result<T, Error>) -> Result<T, Failure> {
+    match panic::catch_unwind(AssertUnwindSafe(run)) {
+        Ok(result) => result.map_err(Failure::Error),
+        Err(payload) => Err(match payload.downcast::<ParseError>() {
+            Ok(error) => Failure::Parse(error),
+            Err(payload) if payload.is::<RuntimeError>() => {
+                let error = payload.downcast::<RuntimeError>().unwrap();
+                Failure::Error(Error::Script {
+                    token: error.token,
+                    message: error.message,
+                })
+            }
+            Err(payload) if payload.is::<LimitError>() => {
+                let error = payload.downcast::<LimitError>().unwrap();
+                Failure::Error(match error.token {
+                    Some(token) => Error::Script {
+                        token,
+                        message: error.limit.to_string(),
+                    },
+                    None => Error::Limit {
+                        limit: error.limit,
+                        line: None,
+                    },
+                })
+            }
+            Err(payload) => Failure::Error(Error::Parse(
+                payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| {
+                        payload
+                            .downcast_ref::<&str>()
+                            .map(|message| message.to_string())
+                    })
+                    .unwrap_or_else(|| String::from("Unknown error")),
+            )),
+        }),
+    }
+}
+
+pub fn report(input: &impl Display, failure: Failure) {
+    match failure {
+        Failure::Parse(error) => error_token_with_help(&error.token, &error.message, &error.help),
+        Failure::Error(Error::Script { token, message }) => error_token(&token, &message),
+        Failure::Error(error @ Error::File { .. }) => error!("{}\n", error),
+        Failure::Error(error) => error!("{}: {}\n", input, error),
+    }
+}
+
+/// Every file with the given extension in a directory and its subdirectories,
+/// relative to the directory
+pub fn files(directory: &Path, extension: &str) -> Vec<PathBuf> {
+    walk(directory)
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|other| other == extension))
+        .collect()
+}
+
+/// Every file in a directory and its subdirectories, relative to the directory.
+/// Directories that cannot be read are skipped.
+fn walk(directory: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut directories = vec![PathBuf::new()];
+
+    while let Some(relative) = directories.pop() {
+        let entries = match fs::read_dir(directory.join(&relative)) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let path = relative.join(entry.file_name());
+
+            if directory.join(&path).is_dir() {
+                directories.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+/// The directory that a pattern starts in, i.e. everything before the first
+/// part with a wildcard. Paths without wildcards are returned as they are.
+pub fn base(path: &Path) -> PathBuf {
+    let base: PathBuf = path
+        .components()
+        .take_while(|component| !is_pattern(&component.as_os_str().to_string_lossy()))
+        .collect();
+
+    match base.as_os_str().is_empty() {
+        true => PathBuf::from("."),
+        false => base,
+    }
+}
+
+/// Whether a path matches a pattern, where `*` matches any part of a name, `?`
+/// matches one character of a name and `**` matches any number of directories
+fn matches(pattern: &Path, path: &Path) -> bool {
+    let parts = |path: &Path| -> Vec<String> {
+        path.components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect()
+    };
+
+    matches_parts(&parts(pattern), &parts(path))
+}
+
+fn matches_parts(pattern: &[String], path: &[String]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, None) => true,
+        (Some((first, rest)), _) if first == "**" => {
+            matches_parts(rest, path) || (!path.is_empty() && matches_parts(pattern, &path[1..]))
+        }
+        (Some((first, rest)), Some((name, names))) => {
+            let first: Vec<char> = first.chars().collect();
+            let name: Vec<char> = name.chars().collect();
+
+            matches_name(&first, &name) && matches_parts(rest, names)
+        }
+        _ => false,
+    }
+}
+
+fn matches_name(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| matches_name(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && matches_name(rest, &name[1..]),
+        Some((first, rest)) => name.first() == Some(first) && matches_name(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use transcript::{
+        executor::{runtime_error, Limit},
+        translate::{Token, Tokenizer},
+    };
+
+    use super::*;
+
+    /// Writes files to a directory of their own, returning the directory
+    fn scripts(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("transcript-{}-{}", test, std::process::id()));
+
+        for (name, contents) in files {
+            Stream::File(directory.join(name)).write(contents).unwrap();
+        }
+
+        directory
+    }
+
+    fn failure<T>(result: Result<T, Failure>) -> Failure {
+        match result {
+            Ok(_) => panic!("Expected a failure"),
+            Err(failure) => failure,
+        }
+    }
+
+    #[test]
+    fn catch_reports_each_way_that_a_script_can_stop() {
+        let token = Tokenizer::from_string(String::from("boom")).tokenize()[0].clone();
+
+        let parse = failure(catch(|| {
+            let mut tokens = Tokenizer::from_string(String::from("let a = (1;")).tokenize();
+            translate::parse(&mut tokens, &Config::default());
+            Ok(())
+        }));
+        assert!(matches!(parse, Failure::Parse(error) if error.token.line == 1));
+
+        let runtime = failure(catch(|| -> Result<(), Error> {
+            runtime_error(&token, "boom")
+        }));
+        assert_eq!(runtime.to_string(), "line 1: boom");
+        assert!(matches!(runtime, Failure::Error(Error::Script { .. })));
+
+        let limit = |token: Option<Token>| {
+            failure(catch(|| -> Result<(), Error> {
+                panic::resume_unwind(Box::new(LimitError {
+                    limit: Limit::Steps(5),
+                    token,
+                }))
+            }))
+        };
+        assert!(matches!(
+            limit(Some(token.clone())),
+            Failure::Error(Error::Script { message, .. }) if message == "Script exceeded its limit of 5 steps"
+        ));
+        assert!(matches!(
+            limit(None),
+            Failure::Error(Error::Limit {
+                limit: Limit::Steps(5),
+                line: None
+            })
+        ));
+
+        let other = failure(catch(|| -> Result<(), Error> {
+            panic::resume_unwind(Box::new(String::from("Unexpected end of file")))
+        }));
+        assert!(
+            matches!(other, Failure::Error(Error::Parse(message)) if message == "Unexpected end of file")
+        );
+
+        let error = failure(catch(|| -> Result<(), Error> {
+            Err(Error::Config(String::from("no locale")))
+        }));
+        assert!(matches!(error, Failure::Error(Error::Config(message)) if message == "no locale"));
+    }
+
+    #[test]
+    fn every_job_runs_when_one_fails() {
+        let directory = scripts(
+            "batch",
+            &[
+                ("scripts/a.script", "print(1);"),
+                ("scripts/b.script", "let a = (1;"),
+                ("scripts/nested/c.script", "print(2);"),
+            ],
+        );
+        let output = directory.join("packed");
+        let config = Config::default();
+
+        let jobs = jobs(
+            &directory.join("scripts").to_string_lossy(),
+            Some(&output.to_string_lossy()),
+            false,
+            "script",
+            "packed",
+        )
+        .unwrap();
+        assert_eq!(jobs.len(), 3);
+
+        let succeeded = run(&jobs, true, |input, contents| {
+            pack_script(input, contents, &config, false)
+        });
+
+        assert!(!succeeded);
+        assert!(output.join("a.packed").is_file());
+        assert!(output.join("a.packed.map").is_file());
+        assert!(!output.join("b.packed").exists());
+        assert!(output.join("nested/c.packed").is_file());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn several_files_need_somewhere_to_go() {
+        let directory = scripts(
+            "batch-output",
+            &[("a.script", "print(1);"), ("b.script", "print(2);")],
+        );
+        let input = directory.to_string_lossy();
+
+        assert_eq!(
+            jobs(&input, Some(STDIO), false, "script", "packed").err(),
+            Some(String::from(
+                "Cannot write 2 files to stdout, give a directory to write them to"
+            ))
+        );
+        assert!(jobs(&input, None, false, "script", "packed").is_err());
+        assert!(jobs(STDIO, None, true, "script", "packed").is_err());
+
+        let in_place = jobs(&input, None, true, "script", "packed").unwrap();
+        assert_eq!(
+            in_place[0].output.path(),
+            Some(directory.join("a.script").as_path())
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn patterns_match_names_and_directories() {
+        let matches = |pattern: &str, path: &str| matches(Path::new(pattern), Path::new(path));
+
+        assert!(matches("*.script", "a.script"));
+        assert!(matches("?.script", "a.script"));
+        assert!(!matches("?.script", "ab.script"));
+        assert!(!matches("*.script", "nested/a.script"));
+        assert!(matches("**/*.script", "a.script"));
+        assert!(matches("**/*.script", "nested/deeper/a.script"));
+        assert!(matches("nested/**/a.*", "nested/deeper/a.packed"));
+        assert!(!matches("nested/**/a.*", "other/a.packed"));
+
+        assert_eq!(base(Path::new("scripts/**/*.script")), Path::new("scripts"));
+        assert_eq!(base(Path::new("*.script")), Path::new("."));
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    io::{self, BufRead, Write},
+    process,
+};
+
+use super::{Frontend, Reason, Resume, Session};
+
+const HELP: &str = "\
+c, continue         run until the next breakpoint
+s, step             step into the next statement
+n, next             step over function calls
+o, out              run until the current function returns
+b, break [file:]N   pause at line N, or list breakpoints if no line is given
+d, delete [file:]N  remove a breakpoint
+bt, where           show the functions that are running
+v, vars             show the variables that can be seen from here
+p, print NAME       show a variable
+q, quit             stop the script
+An empty line repeats the last command.";
+
+/// Asks what to do on the terminal whenever the script pauses
+#[derive(Default)]
+pub struct Console {
+    last_command: String,
+}
+
+impl Console {
+    fn show_location(&self, reason: Reason, session: &Session) {
+        let frame = session.stack.last().unwrap();
+        let token = match &frame.token {
+            Some(token) => token,
+            None => return,
+        };
+
+        let file = token
+            .path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let reason = match reason {
+            Reason::Entry => "entry",
+            Reason::Breakpoint => "breakpoint",
+            Reason::Step => "step",
+        };
+
+        println!(
+            "Paused in {} at {}:{} ({})",
+            session.renderer.name(&frame.name),
+            file,
+            token.line,
+            reason
+        );
+        println!(
+            "{} | {}",
+            token.line,
+            session.renderer.source(&token.line_contents)
+        );
+    }
+
+    fn breakpoints(&self, session: &Session) {
+        if session.breakpoints.values().all(|lines| lines.is_empty()) {
+            println!("No breakpoints");
+        }
+
+        for (path, lines) in &session.breakpoints {
+            for line in lines {
+                println!("{}:{}", path.display(), line);
+            }
+        }
+    }
+
+    fn backtrace(&self, session: &Session) {
+        for (index, frame) in session.stack.iter().rev().enumerate() {
+            let location = match &frame.token {
+                Some(token) => format!(" at line {}", token.line),
+                None => String::new(),
+            };
+
+            println!(
+                "#{} {}{}",
+                index,
+                session.renderer.name(&frame.name),
+                location
+            );
+        }
+    }
+
+    fn variables(&self, session: &Session) {
+        for (scope, variables) in session.scopes(session.stack.len() - 1) {
+            println!("{}:", scope);
+
+            for (name, value) in variables {
+                println!("  {} = {}", name, value);
+            }
+        }
+    }
+
+    fn print(&self, session: &Session, name: &str) {
+        // The innermost scope wins, like it does in the script
+        let found = session
+            .scopes(session.stack.len() - 1)
+            .into_iter()
+            .flat_map(|(_, variables)| variables)
+            .find(|(variable, _)| variable == name);
+
+        match found {
+            Some((_, value)) => println!("{} = {}", name, value),
+            None => println!("No variable named {}", name),
+        }
+    }
+}
+
+impl Frontend for Console {
+    fn paused(&mut self, reason: Reason, session: &mut Session) -> Resume {
+        self.show_location(reason, session);
+
+        let stdin = io::stdin();
+
+        loop {
+            print!("(debug) ");
+            io::stdout().flush().unwrap();
+
+            let mut line = String::new();
+
+            // Nothing left to read, so nobody can tell the script to carry on
+            if stdin.lock().read_line(&mut line).unwrap() == 0 {
+                println!();
+                return Resume::Continue;
+            }
+
+            let line = match line.trim() {
+                "" => self.last_command.to_string(),
+                line => line.to_string(),
+            };
+            self.last_command = line.to_string();
+
+            let (command, argument) = match line.split_once(' ') {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (line.as_str(), ""),
+            };
+
+            match command {
+                "c" | "continue" => return Resume::Continue,
+                "s" | "step" => return Resume::StepIn,
+                "n" | "next" => return Resume::StepOver,
+                "o" | "out" => return Resume::StepOut,
+                "b" | "break" if argument.is_empty() => self.breakpoints(session),
+                "b" | "break" | "d" | "delete" => match session.location(argument) {
+                    Some((path, line)) if command.starts_with('b') => {
+                        session.breakpoints.entry(path).or_default().insert(line);
+                    }
+                    Some((path, line)) => {
+                        session.breakpoints.entry(path).or_default().remove(&line);
+                    }
+                    None => println!("Expected a line number, or a file and a line number"),
+                },
+                "bt" | "where" => self.backtrace(session),
+                "v" | "vars" => self.variables(session),
+                "p" | "print" => self.print(session, argument),
+                "q" | "quit" => process::exit(0),
+                "h" | "help" => println!("{}", HELP),
+                "" => (),
+                _ => println!("Unknown command {}, type help for a list", command),
+            }
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    io::{self, BufRead, BufReader, Read, Stdin, Write},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+};
+
+use serde_json::{json, Value as Json};
+use transcript::file::Span;
+
+use super::{canonical, Frontend, Reason, Resume, Session};
+
+/// Every script runs on one thread, which is the only thread the editor is told
+/// about
+const THREAD_ID: i64 = 1;
+
+/// Variable references are made of the frame and the scope within that frame
+const SCOPES_PER_FRAME: i64 = 100;
+
+/// The connection to the editor. Messages are JSON with a `Content-Length`
+/// header, read from stdin and written to stdout.
+struct Channel {
+    input: BufReader<Stdin>,
+    seq: i64,
+}
+
+impl Channel {
+    /// Returns `None` once the editor has closed the connection
+    fn read(&mut self) -> Option<Json> {
+        let mut length = None;
+
+        loop {
+            let mut header = String::new();
+            if self.input.read_line(&mut header).ok()? == 0 {
+                return None;
+            }
+
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+
+        let mut body = vec![0; length?];
+        self.input.read_exact(&mut body).ok()?;
+
+        serde_json::from_slice(&body).ok()
+    }
+
+    fn send(&mut self, mut message: Json) {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        let body = message.to_string();
+        let mut stdout = io::stdout().lock();
+        write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        stdout.flush().unwrap();
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }));
+    }
+
+    fn fail(&mut self, request: &Json, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }));
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+}
+
+/// What the editor asked for before the script started
+pub struct Launch {
+    pub breakpoints: BTreeMap<PathBuf, BTreeSet<usize>>,
+    pub stop_on_entry: bool,
+}
+
+/// Serves the Debug Adapter Protocol, so that editors can debug scripts
+pub struct Dap {
+    channel: Rc<RefCell<Channel>>,
+}
+
+impl Default for Dap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dap {
+    pub fn new() -> Dap {
+        Dap {
+            channel: Rc::new(RefCell::new(Channel {
+                input: BufReader::new(io::stdin()),
+                seq: 0,
+            })),
+        }
+    }
+
+    /// A handle for sending events once the debugger owns the frontend
+    pub fn events(&self) -> Events {
+        Events(self.channel.clone())
+    }
+
+    /// Handles requests until the editor has finished setting up
+    pub fn configure(&mut self) -> Launch {
+        let mut channel = self.channel.borrow_mut();
+        let mut launch = Launch {
+            breakpoints: BTreeMap::new(),
+            stop_on_entry: false,
+        };
+
+        loop {
+            let request = match channel.read() {
+                Some(request) => request,
+                None => process::exit(0),
+            };
+
+            match request["command"].as_str().unwrap_or_default() {
+                "initialize" => {
+                    channel.respond(
+                        &request,
+                        json!({ "supportsConfigurationDoneRequest": true }),
+                    );
+                    channel.event("initialized", json!({}));
+                }
+                "launch" | "attach" => {
+                    launch.stop_on_entry = request["arguments"]["stopOnEntry"]
+                        .as_bool()
+                        .unwrap_or(false);
+                    channel.respond(&request, json!({}));
+                }
+                "setBreakpoints" => {
+                    let body = set_breakpoints(&request, &mut launch.breakpoints);
+                    channel.respond(&request, body);
+                }
+                "threads" => channel.respond(&request, threads()),
+                "configurationDone" => {
+                    channel.respond(&request, json!({}));
+                    return launch;
+                }
+                "disconnect" => {
+                    channel.respond(&request, json!({}));
+                    process::exit(0);
+                }
+                _ => channel.respond(&request, json!({})),
+            }
+        }
+    }
+}
+
+/// Sends events to the editor. Writing to it sends the text as script output,
+/// since stdout is taken up by the protocol.
+#[derive(Clone)]
+pub struct Events(Rc<RefCell<Channel>>);
+
+impl Events {
+    /// Tells the editor that the script has finished, with the error that
+    /// stopped it if it failed
+    pub fn finish(&self, error: Option<&str>) {
+        let mut channel = self.0.borrow_mut();
+
+        if let Some(error) = error {
+            channel.event(
+                "output",
+                json!({ "category": "stderr", "output": format!("{}\n", error) }),
+            );
+        }
+
+        channel.event("terminated", json!({}));
+        channel.event("exited", json!({ "exitCode": i32::from(error.is_some()) }));
+    }
+}
+
+impl Write for Events {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().event(
+            "output",
+            json!({ "category": "stdout", "output": String::from_utf8_lossy(buf) }),
+        );
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn threads() -> Json {
+    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })
+}
+
+/// Replaces the breakpoints of one file, which is how editors send them
+fn set_breakpoints(request: &Json, breakpoints: &mut BTreeMap<PathBuf, BTreeSet<usize>>) -> Json {
+    let arguments = &request["arguments"];
+    let path = canonical(Path::new(
+        arguments["source"]["path"].as_str().unwrap_or_default(),
+    ));
+
+    let lines: BTreeSet<usize> = arguments["breakpoints"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|breakpoint| breakpoint["line"].as_u64())
+        .map(|line| line as usize)
+        .collect();
+
+    let body = json!({
+        "breakpoints": lines
+            .iter()
+            .map(|line| json!({ "verified": true, "line": line }))
+            .collect::<Vec<Json>>()
+    });
+
+    breakpoints.insert(path, lines);
+    body
+}
+
+fn stack_trace(session: &Session) -> Json {
+    let frames: Vec<Json> = session
+        .stack
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(id, frame)| {
+            let mut json = json!({
+                "id": id,
+                "name": session.renderer.name(&frame.name),
+                "line": 0,
+                "column": 0,
+            });
+
+            if let Some(token) = &frame.token {
+                // Packed scripts are shown in the file that they were packed
+                // from, which is where the breakpoints were set
+                let (path, span) = match session.sources.original(token) {
+                    Some((file, span)) => (Some(file), span),
+                    None => (token.path.as_deref(), Span::from(token)),
+                };
+
+                json["line"] = json!(span.line);
+                json["column"] = json!(span.start + 1);
+
+                if let Some(path) = path {
+                    json["source"] = json!({
+                        "name": path.file_name().map(|name| name.to_string_lossy()),
+                        "path": path.display().to_string(),
+                    });
+                }
+            }
+
+            json
+        })
+        .collect();
+
+    json!({ "totalFrames": frames.len(), "stackFrames": frames })
+}
+
+fn scopes(request: &Json, session: &Session) -> Json {
+    let frame = request["arguments"]["frameId"].as_i64().unwrap_or_default();
+
+    let scopes: Vec<Json> = session
+        .scopes(frame as usize)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, _))| {
+            json!({
+                "name": name,
+                "variablesReference": frame * SCOPES_PER_FRAME + index as i64 + 1,
+                "expensive": false,
+            })
+        })
+        .collect();
+
+    json!({ "scopes": scopes })
+}
+
+fn variables(request: &Json, session: &Session) -> Json {
+    let reference = request["arguments"]["variablesReference"]
+        .as_i64()
+        .unwrap_or_default()
+        - 1;
+    let (frame, scope) = (reference / SCOPES_PER_FRAME, reference % SCOPES_PER_FRAME);
+
+    let variables: Vec<Json> = session
+        .scopes(frame as usize)
+        .into_iter()
+        .nth(scope as usize)
+        .map(|(_, variables)| variables)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| json!({ "name": name, "value": value, "variablesReference": 0 }))
+        .collect();
+
+    json!({ "variables": variables })
+}
+
+impl Frontend for Dap {
+    fn paused(&mut self, reason: Reason, session: &mut Session) -> Resume {
+        let mut channel = self.channel.borrow_mut();
+
+        let reason = match reason {
+            Reason::Entry => "entry",
+            Reason::Breakpoint => "breakpoint",
+            Reason::Step => "step",
+        };
+        channel.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        );
+
+        loop {
+            let request = match channel.read() {
+                Some(request) => request,
+                None => process::exit(0),
+            };
+
+            let resume = match request["command"].as_str().unwrap_or_default() {
+                "threads" => {
+                    channel.respond(&request, threads());
+                    continue;
+                }
+                "stackTrace" => {
+                    channel.respond(&request, stack_trace(session));
+                    continue;
+                }
+                "scopes" => {
+                    channel.respond(&request, scopes(&request, session));
+                    continue;
+                }
+                "variables" => {
+                    channel.respond(&request, variables(&request, session));
+                    continue;
+                }
+                "setBreakpoints" => {
+                    let body = set_breakpoints(&request, &mut session.breakpoints);
+                    channel.respond(&request, body);
+                    continue;
+                }
+                "disconnect" => {
+                    channel.respond(&request, json!({}));
+                    process::exit(0);
+                }
+                "continue" => Resume::Continue,
+                "next" => Resume::StepOver,
+                "stepIn" => Resume::StepIn,
+                "stepOut" => Resume::StepOut,
+                _ => {
+                    channel.fail(&request, "Not supported while paused");
+                    continue;
+                }
+            };
+
+            channel.respond(&request, json!({ "allThreadsContinued": true }));
+            return resume;
+        }
+    }
+}
@@ -0,0 +1,516 @@
+//! A step debugger for scripts run by the tree walker. The debugger watches the
+//! script through `executor::observe` and pauses before statements, handing
+//! control to a frontend: either a prompt on the terminal (`run --debug`) or a
+//! Debug Adapter Protocol server for editors (`run --dap`).
+//!
+//! Packing a script lays it out again, so its lines move. Breakpoints set in
+//! the file that a packed script was packed from are found in the packed
+//! script through the source map written next to it by `pack --source-map`.
+//! Without a source map, breakpoints only hit in the file that is running.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use transcript::{
+    executor::{Environment, Observer, Value},
+    file::{unpack_with_glossary, Glossary, SourceMap, Span},
+    translate::{Token, AST},
+    Config,
+};
+
+pub use self::console::Console;
+pub use self::dap::Dap;
+
+mod console;
+mod dap;
+
+/// A function that is running, from the script itself at the bottom of the
+/// stack to the function that is running now at the top
+pub struct Frame {
+    pub name: String,
+    /// The statement that is about to run in this frame, or the statement that
+    /// called the frame above it
+    pub token: Option<Token>,
+    /// The environment the statement runs in. Rust functions have none
+    pub env: Option<Rc<RefCell<Environment>>>,
+}
+
+/// Why the script paused
+#[derive(Clone, Copy)]
+pub enum Reason {
+    Entry,
+    Breakpoint,
+    Step,
+}
+
+/// How to carry on after a pause
+pub enum Resume {
+    /// Run until the next breakpoint
+    Continue,
+    /// Pause at the next statement, even if it is inside of a function call
+    StepIn,
+    /// Pause at the next statement in this function or the one that called it
+    StepOver,
+    /// Pause once the current function has returned
+    StepOut,
+}
+
+pub trait Frontend {
+    /// Called whenever the script pauses, returning how to carry on
+    fn paused(&mut self, reason: Reason, session: &mut Session) -> Resume;
+}
+
+/// What a frontend can look at and change while the script is paused
+pub struct Session {
+    pub stack: Vec<Frame>,
+    /// Line numbers to pause at, by canonical path
+    pub breakpoints: BTreeMap<PathBuf, BTreeSet<usize>>,
+    /// The script that was run, which breakpoints without a file refer to
+    pub script: PathBuf,
+    pub renderer: Renderer,
+    pub sources: Sources,
+}
+
+impl Session {
+    /// The scopes visible from a frame, innermost first. The last scope is
+    /// always the global one.
+    pub fn scopes(&self, frame: usize) -> Vec<(&'static str, Vec<(String, String)>)> {
+        let mut scopes = Vec::new();
+        let mut env = self.stack.get(frame).and_then(|frame| frame.env.clone());
+
+        while let Some(current) = env {
+            let enclosing = current.borrow().enclosing();
+
+            // The outermost scope only holds the builtins, around the globals
+            let global = match &enclosing {
+                Some(enclosing) => enclosing.borrow().enclosing().is_none(),
+                None => break,
+            };
+            let name = match (global, scopes.is_empty()) {
+                (true, _) => "Globals",
+                (false, true) => "Locals",
+                (false, false) => "Closure",
+            };
+
+            scopes.push((name, self.variables(&current.borrow())));
+            env = enclosing;
+        }
+
+        scopes
+    }
+
+    /// The variables of a scope, rendered in the language of the reader.
+    /// Builtins and the hidden globals holding imported modules are left out,
+    /// since they are the same in every script.
+    fn variables(&self, env: &Environment) -> Vec<(String, String)> {
+        env.variables()
+            .into_iter()
+            .filter(|(name, value)| {
+                !matches!(value, Value::RustFunction { .. }) && !name.contains(char::is_whitespace)
+            })
+            .map(|(name, value)| (self.renderer.name(&name), self.renderer.value(&value)))
+            .collect()
+    }
+
+    /// Finds a breakpoint location written as `line` or `file:line`
+    pub fn location(&self, location: &str) -> Option<(PathBuf, usize)> {
+        match location.rsplit_once(':') {
+            Some((path, line)) => Some((canonical(Path::new(path)), line.parse().ok()?)),
+            None => Some((self.script.clone(), location.parse().ok()?)),
+        }
+    }
+}
+
+/// The source maps of the packed scripts that have run, which lead back to the
+/// files that they were packed from
+#[derive(Default)]
+pub struct Sources {
+    /// The file each script was packed from and the map to it, by the canonical
+    /// path of the script. Scripts without a source map have none
+    maps: BTreeMap<PathBuf, Option<(PathBuf, SourceMap)>>,
+}
+
+impl Sources {
+    /// Reads the source map of a script the first time that it runs
+    fn load(&mut self, script: &Path) {
+        if self.maps.contains_key(script) {
+            return;
+        }
+
+        let map = SourceMap::load(script)
+            .ok()
+            .flatten()
+            .and_then(|map| Some((canonical(map.file.as_ref()?), map)));
+        self.maps.insert(script.to_path_buf(), map);
+    }
+
+    /// Where a token of a packed script came from in the file that it was
+    /// packed from, if the script has a source map
+    pub fn original(&self, token: &Token) -> Option<(&Path, Span)> {
+        let script = token.path.as_ref()?;
+        let (file, map) = self.maps.get(script.as_ref())?.as_ref()?;
+
+        Some((file, map.original(Span::from(token))?))
+    }
+}
+
+pub fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Shows names, values and source in the language of the reader, the same way
+/// that `unpack` would
+pub struct Renderer {
+    config: Config,
+    glossary: Option<Glossary>,
+}
+
+impl Renderer {
+    pub fn new(config: &Config) -> Renderer {
+        Renderer {
+            config: config.clone(),
+            glossary: Glossary::load(config).ok().flatten(),
+        }
+    }
+
+    pub fn source(&self, source: &str) -> String {
+        unpack_with_glossary(source.to_string(), &self.config, self.glossary.as_ref())
+    }
+
+    pub fn name(&self, name: &str) -> String {
+        self.source(name)
+    }
+
+    pub fn value(&self, value: &Value) -> String {
+        let config = &self.config;
+
+        match value {
+            Value::String(string) => format!("{:?}", string),
+            Value::Option(None) => config.none_keyword.to_string(),
+            Value::Option(Some(value)) => {
+                format!("{}({})", config.builtin_name("some"), self.value(value))
+            }
+            Value::List(items) => format!(
+                "[{}]",
+                items
+                    .borrow()
+                    .iter()
+                    .map(|item| self.value(item))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Value::Map(entries) => format!(
+                "{{{}}}",
+                entries
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| format!("{:?}: {}", key, self.value(value)))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Value::Function { name, args, .. } => format!(
+                "{} {}({})",
+                config.function_keyword,
+                self.name(name),
+                args.iter()
+                    .map(|arg| self.name(arg))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Value::RustFunction { name, args, .. } => format!(
+                "{} {}({})",
+                config.function_keyword,
+                self.name(name),
+                args.join(", ")
+            ),
+            value => value.to_string(),
+        }
+    }
+}
+
+enum Mode {
+    Run,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+}
+
+pub struct Debugger<F: Frontend> {
+    session: Session,
+    frontend: F,
+    mode: Mode,
+    started: bool,
+}
+
+impl<F: Frontend> Debugger<F> {
+    /// Creates a debugger for `script`. If `stop_on_entry` is set, the script
+    /// pauses before its first statement.
+    pub fn new(script: &Path, config: &Config, frontend: F, stop_on_entry: bool) -> Debugger<F> {
+        Debugger {
+            session: Session {
+                stack: vec![Frame {
+                    name: String::from("<script>"),
+                    token: None,
+                    env: None,
+                }],
+                breakpoints: BTreeMap::new(),
+                script: canonical(script),
+                renderer: Renderer::new(config),
+                sources: Sources::default(),
+            },
+            frontend,
+            mode: match stop_on_entry {
+                true => Mode::StepIn,
+                false => Mode::Run,
+            },
+            started: false,
+        }
+    }
+
+    pub fn session(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Whether there is a breakpoint on the line of `token`, either in the
+    /// script that is running or in the file that it was packed from
+    fn at_breakpoint(&self, token: &Token) -> bool {
+        let breakpoint = |path: &Path, line: usize| {
+            self.session
+                .breakpoints
+                .get(path)
+                .is_some_and(|lines| lines.contains(&line))
+        };
+
+        token
+            .path
+            .as_ref()
+            .is_some_and(|path| breakpoint(path, token.line))
+            || self
+                .session
+                .sources
+                .original(token)
+                .is_some_and(|(file, span)| breakpoint(file, span.line))
+    }
+}
+
+impl<F: Frontend> Observer for Debugger<F> {
+    fn statement(&mut self, statement: &AST, env: &Rc<RefCell<Environment>>) {
+        // Statements without a token (e.g. comments) cannot be shown, so they
+        // are never paused at
+        let token = match statement.token() {
+            Some(token) => token,
+            None => return,
+        };
+
+        if let Some(path) = &token.path {
+            self.session.sources.load(path);
+        }
+
+        let depth = self.session.stack.len();
+        let frame = self.session.stack.last_mut().unwrap();
+        frame.token = Some(token.clone());
+        frame.env = Some(env.clone());
+
+        let reason = if self.at_breakpoint(token) {
+            Some(Reason::Breakpoint)
+        } else {
+            match self.mode {
+                Mode::StepIn if !self.started => Some(Reason::Entry),
+                Mode::StepIn => Some(Reason::Step),
+                Mode::StepOver(from) if depth <= from => Some(Reason::Step),
+                Mode::StepOut(from) if depth < from => Some(Reason::Step),
+                _ => None,
+            }
+        };
+
+        self.started = true;
+
+        if let Some(reason) = reason {
+            self.mode = match self.frontend.paused(reason, &mut self.session) {
+                Resume::Continue => Mode::Run,
+                Resume::StepIn => Mode::StepIn,
+                Resume::StepOver => Mode::StepOver(depth),
+                Resume::StepOut => Mode::StepOut(depth),
+            };
+        }
+    }
+
+    fn enter(&mut self, function: &str) {
+        self.session.stack.push(Frame {
+            name: function.to_string(),
+            token: None,
+            env: None,
+        });
+    }
+
+    fn exit(&mut self) {
+        self.session.stack.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, fs};
+
+    use transcript::{executor::observe, module::Loader};
+
+    use super::*;
+
+    const SCRIPT: &str = "\
+function double(n) {
+    let twice = n * 2;
+    return twice;
+};
+let a = double(1);
+let b = double(a);
+print(b);
+";
+
+    /// Carries on the way it is told to and remembers where the script paused
+    #[derive(Default)]
+    struct Recorder {
+        resumes: VecDeque<Resume>,
+        /// The reason, the line and the functions that were running
+        pauses: Vec<(&'static str, usize, Vec<String>)>,
+        /// The local variables at each pause
+        locals: Vec<Vec<(String, String)>>,
+    }
+
+    impl Frontend for Recorder {
+        fn paused(&mut self, reason: Reason, session: &mut Session) -> Resume {
+            let reason = match reason {
+                Reason::Entry => "entry",
+                Reason::Breakpoint => "breakpoint",
+                Reason::Step => "step",
+            };
+            let frame = session.stack.len() - 1;
+
+            self.pauses.push((
+                reason,
+                session.stack[frame].token.as_ref().unwrap().line,
+                session
+                    .stack
+                    .iter()
+                    .map(|frame| frame.name.clone())
+                    .collect(),
+            ));
+            self.locals.push(
+                session
+                    .scopes(frame)
+                    .into_iter()
+                    .find(|(scope, _)| *scope == "Locals")
+                    .map(|(_, variables)| variables)
+                    .unwrap_or_default(),
+            );
+
+            self.resumes.pop_front().unwrap_or(Resume::Continue)
+        }
+    }
+
+    /// Runs `SCRIPT` under a debugger, returning what its frontend recorded
+    fn debug(
+        test: &str,
+        resumes: Vec<Resume>,
+        stop_on_entry: bool,
+        breakpoints: &[usize],
+    ) -> Recorder {
+        let directory =
+            std::env::temp_dir().join(format!("transcript-{}-{}", test, std::process::id()));
+        let script = directory.join("script.script");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&script, SCRIPT).unwrap();
+
+        let config = Config::default();
+        let frontend = Recorder {
+            resumes: resumes.into(),
+            ..Default::default()
+        };
+        let debugger = Rc::new(RefCell::new(Debugger::new(
+            &script,
+            &config,
+            frontend,
+            stop_on_entry,
+        )));
+        debugger
+            .borrow_mut()
+            .session()
+            .breakpoints
+            .insert(canonical(&script), breakpoints.iter().copied().collect());
+
+        let mut loader = Loader::new(&config, false);
+        loader.set_output(Rc::new(RefCell::new(Box::new(std::io::sink()))));
+        observe(debugger.clone(), || loader.run(&script)).unwrap();
+
+        fs::remove_dir_all(directory).unwrap();
+        Rc::try_unwrap(debugger).ok().unwrap().into_inner().frontend
+    }
+
+    fn stack(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn breakpoints_pause_every_time_their_line_runs() {
+        let recorder = debug("debug-breakpoints", Vec::new(), false, &[3]);
+
+        assert_eq!(
+            recorder.pauses,
+            [
+                ("breakpoint", 3, stack(&["<script>", "double"])),
+                ("breakpoint", 3, stack(&["<script>", "double"])),
+            ]
+        );
+        assert!(recorder.locals[0].contains(&(String::from("twice"), String::from("2"))));
+        assert!(recorder.locals[1].contains(&(String::from("twice"), String::from("4"))));
+    }
+
+    #[test]
+    fn steps_go_into_over_and_out_of_functions() {
+        let recorder = debug(
+            "debug-steps",
+            vec![
+                Resume::StepOver,
+                Resume::StepIn,
+                Resume::StepOut,
+                Resume::StepOver,
+            ],
+            true,
+            &[],
+        );
+
+        assert_eq!(
+            recorder.pauses,
+            [
+                ("entry", 1, stack(&["<script>"])),
+                ("step", 5, stack(&["<script>"])),
+                ("step", 2, stack(&["<script>", "double"])),
+                ("step", 6, stack(&["<script>"])),
+                ("step", 7, stack(&["<script>"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn stepping_over_a_call_stops_at_its_breakpoints() {
+        let recorder = debug(
+            "debug-step-over",
+            vec![Resume::StepOver, Resume::StepOver, Resume::Continue],
+            true,
+            &[3],
+        );
+
+        assert_eq!(
+            recorder.pauses,
+            [
+                ("entry", 1, stack(&["<script>"])),
+                ("step", 5, stack(&["<script>"])),
+                ("breakpoint", 3, stack(&["<script>", "double"])),
+                ("breakpoint", 3, stack(&["<script>", "double"])),
+            ]
+        );
+    }
+}
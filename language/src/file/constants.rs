@@ -1,2 +0,0 @@
-pub const VARIABLE_DEFINITION_CHARACTER: &'static str = ""; // E000
-pub const FUNCTION_DEFINITION_CHARACTER: &'static str = ""; // E001
@@ -1,7 +0,0 @@
-mod constants;
-mod pack;
-mod unpack;
-
-pub use constants::*;
-pub use pack::*;
-pub use unpack::*;
@@ -1,23 +1,41 @@
 use std::{
-    fs::File,
-    io::{Read, Write},
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    time::{Duration, Instant},
 };
 
+use batch::{Failure, Job, Stream, STDIO};
 use clap::{Parser, Subcommand};
-use executor::{execute, Environment};
+use debug::{Console, Dap, Debugger};
 use fern::{
     colors::{Color, ColoredLevelConfig},
     Dispatch,
 };
-use file::{pack, unpack};
-use utils::Config;
+use log::{error, info};
+use profile::{Profiler, Tracer};
+use transcript::{
+    executor::{execute, observe},
+    file::{convert, is_packed, unpack},
+    module::{resolve, Loader},
+    stdlib::{self, Capabilities},
+    translate,
+    utils::{error_token, error_token_with_help, warn_token, Config, Locale},
+    vm, Error,
+};
+use watch::Watcher;
 
-use crate::executor::Value;
+mod batch;
+mod debug;
+mod profile;
+mod watch;
 
-mod executor;
-mod file;
-mod translate;
-mod utils;
+/// The extension of scripts that people read and edit
+const SCRIPT_EXTENSION: &str = "script";
+/// The extension of packed scripts, which are what gets shared
+const PACKED_EXTENSION: &str = "packed";
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -25,96 +43,543 @@ mod utils;
 struct Cli {
     #[clap(subcommand)]
     command: Commands,
+    /// Read this config file on top of any that are found next to the script
+    /// or in the user's config directory
+    #[clap(long, global = true, value_name = "FILE")]
+    config: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Pack scripts so that they can be shared. INPUT is a script, a
+    /// directory of scripts, a pattern like 'scripts/**/*.script' or `-` for
+    /// stdin, and OUTPUT is a file, a directory or `-` for stdout (the
+    /// default for a single script). Exits with 1 if any script fails, or 2
+    /// if the arguments do not fit together.
     Pack {
         #[clap(value_parser)]
         input: String,
         #[clap(value_parser)]
-        output: String,
+        output: Option<String>,
+        /// Replace each input with its packed copy, instead of writing to
+        /// OUTPUT
+        #[clap(long, conflicts_with = "output")]
+        in_place: bool,
+        /// Write where each token of the output came from in the input to
+        /// OUTPUT.map
+        #[clap(long)]
+        source_map: bool,
+        /// Work out which of the installed locales the input is written in,
+        /// instead of assuming it is written in the configured one
+        #[clap(long)]
+        detect_locale: bool,
     },
+    /// Unpack packed scripts into the configured locale. INPUT and OUTPUT
+    /// work the same way as they do for `pack`
     Unpack {
         #[clap(value_parser)]
         input: String,
         #[clap(value_parser)]
-        output: String,
+        output: Option<String>,
+        /// Replace each input with its unpacked copy, instead of writing to
+        /// OUTPUT
+        #[clap(long, conflicts_with = "output")]
+        in_place: bool,
+        /// Write where each token of the output came from in the input to
+        /// OUTPUT.map
+        #[clap(long)]
+        source_map: bool,
     },
     Run {
         #[clap(value_parser)]
         file: String,
+        /// Compile the script to bytecode and run it on the virtual machine
+        #[clap(long)]
+        vm: bool,
+        /// Seed `random` with a fixed seed, stop `clock` and leave out the
+        /// builtins for files and environment variables, so that the script
+        /// prints the same thing every time it runs
+        #[clap(long)]
+        deterministic: bool,
+        /// Pause before the first statement and step through the script from
+        /// the terminal
+        #[clap(long, conflicts_with_all = &["vm", "dap"])]
+        debug: bool,
+        /// Serve the Debug Adapter Protocol over stdin and stdout, for
+        /// debugging from an editor
+        #[clap(long, conflicts_with = "vm")]
+        dap: bool,
+        /// Log every statement and function call as it runs, with timings
+        #[clap(long, conflicts_with_all = &["vm", "debug", "dap"])]
+        trace: bool,
+        /// Count the calls to each function and time them, writing the time
+        /// spent in each chain of calls to FILE as folded stacks for flamegraph
+        /// tools
+        #[clap(long, value_name = "FILE", conflicts_with_all = &["vm", "debug", "dap"])]
+        profile: Option<String>,
+    },
+    /// Translate a script from one locale to another. If INPUT is a directory,
+    /// every script in it is translated into the same place under OUTPUT
+    Translate {
+        /// The code of the locale that the input is written in, e.g. "fi"
+        #[clap(long)]
+        from: String,
+        /// The code of the locale to translate to, e.g. "de"
+        #[clap(long)]
+        to: String,
+        #[clap(value_parser)]
+        input: String,
+        #[clap(value_parser)]
+        output: String,
+    },
+    /// Pack scripts in DIRECTORY whenever they change, and unpack packed
+    /// scripts whenever they change, so that each `.script` file and its
+    /// `.packed` file stay the same
+    Watch {
+        #[clap(value_parser, default_value = ".")]
+        directory: String,
+        /// How often to check for changes, in milliseconds
+        #[clap(long, default_value_t = 500)]
+        interval: u64,
+    },
+    /// Time a script with both the tree walking interpreter and the virtual
+    /// machine. Scripts that are not packed are packed before they are timed.
+    Bench {
+        #[clap(value_parser)]
+        file: String,
+        #[clap(short, long, default_value_t = 20)]
+        iterations: u32,
+    },
+    /// Look at the config that scripts are run with
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommands,
     },
 }
 
-fn main() {
-    setup_logger().unwrap();
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Report every problem with the config for scripts in PATH, which is
+    /// either a script or a directory
+    Check {
+        #[clap(value_parser, default_value = ".")]
+        path: String,
+    },
+}
 
+impl Commands {
+    /// The script that a command works on
+    fn file(&self) -> &str {
+        match self {
+            Commands::Pack { input, .. }
+            | Commands::Unpack { input, .. }
+            | Commands::Translate { input, .. } => input,
+            Commands::Run { file, .. } | Commands::Bench { file, .. } => file,
+            Commands::Watch { directory, .. } => directory,
+            Commands::Config {
+                command: ConfigCommands::Check { path },
+            } => path,
+        }
+    }
+
+    /// Whether the command writes a script to stdout, which leaves stderr for
+    /// logging
+    fn writes_stdout(&self) -> bool {
+        match self {
+            Commands::Pack {
+                input,
+                output,
+                in_place,
+                ..
+            }
+            | Commands::Unpack {
+                input,
+                output,
+                in_place,
+                ..
+            } => {
+                let single = input == STDIO || Path::new(input).is_file();
+                !in_place && output.as_deref().map_or(single, |output| output == STDIO)
+            }
+            Commands::Run { dap, .. } => *dap,
+            _ => false,
+        }
+    }
+}
+
+fn main() {
     let args = Cli::parse();
-    let config = Config::new();
 
-    match args.command {
-        Commands::Pack { input, output } => {
-            let mut file = File::open(input).unwrap();
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
+    // Scripts written to stdout and the debug adapter protocol take up stdout
+    setup_logger(args.command.writes_stdout()).unwrap();
 
-            let tokenizer = translate::Tokenizer::from_string(contents);
-            let mut tokens = tokenizer.tokenize();
+    // Config is found relative to the script, so that commands work from any
+    // directory. Patterns are looked up from the directory that they start in.
+    let file = &batch::base(Path::new(args.command.file()));
+    let directory = match file.parent() {
+        _ if file.is_dir() => file,
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    let config = Config::discover(directory, args.config.as_deref().map(Path::new));
+
+    if let Commands::Config {
+        command: ConfigCommands::Check { .. },
+    } = args.command
+    {
+        check_config(config);
+    }
+
+    let config = config.unwrap_or_else(exit);
+
+    // Scripts stop with an error by unwinding, which is caught here unless the
+    // command has something to finish first (e.g. writing a profile)
+    batch::catch(|| {
+        command(args.command, &config);
+        Ok(())
+    })
+    .unwrap_or_else(fail);
+}
+
+fn command(command: Commands, config: &Config) {
+    match command {
+        Commands::Pack {
+            input,
+            output,
+            in_place,
+            source_map,
+            detect_locale,
+        } => {
+            let jobs = jobs(
+                &input,
+                output,
+                in_place,
+                source_map,
+                SCRIPT_EXTENSION,
+                PACKED_EXTENSION,
+            );
+
+            if !batch::run(&jobs, source_map, |input, contents| {
+                batch::pack_script(input, contents, config, detect_locale)
+            }) {
+                process::exit(1);
+            }
+        }
+        Commands::Unpack {
+            input,
+            output,
+            in_place,
+            source_map,
+        } => {
+            let jobs = jobs(
+                &input,
+                output,
+                in_place,
+                source_map,
+                PACKED_EXTENSION,
+                SCRIPT_EXTENSION,
+            );
+
+            if !batch::run(&jobs, source_map, |_, contents| unpack(contents, config)) {
+                process::exit(1);
+            }
+        }
+        Commands::Run {
+            file,
+            vm,
+            deterministic,
+            debug,
+            dap,
+            trace,
+            profile,
+        } => {
+            let path = Path::new(&file);
+            let mut loader = Loader::new(config, vm);
+
+            if deterministic {
+                loader.set_capabilities(Capabilities::deterministic());
+            }
+
+            if debug {
+                let debugger = Debugger::new(path, config, Console::default(), true);
+                observe(Rc::new(RefCell::new(debugger)), || loader.run(path)).unwrap_or_else(exit);
+            } else if dap {
+                let mut frontend = Dap::new();
+                let events = frontend.events();
+                let launch = frontend.configure();
+
+                let mut debugger = Debugger::new(path, config, frontend, launch.stop_on_entry);
+                debugger.session().breakpoints = launch.breakpoints;
+
+                // Stdin is taken up by the protocol as well
+                loader.set_output(Rc::new(RefCell::new(Box::new(events.clone()))));
+                loader.set_capabilities(Capabilities {
+                    stdin: false,
+                    ..if deterministic {
+                        Capabilities::deterministic()
+                    } else {
+                        Capabilities::all()
+                    }
+                });
+
+                // The editor is told that the script has finished even when it
+                // fails, so that it stops waiting on the session
+                let result =
+                    batch::catch(|| observe(Rc::new(RefCell::new(debugger)), || loader.run(path)));
+                events.finish(result.as_ref().err().map(ToString::to_string).as_deref());
+                result.unwrap_or_else(fail);
+            } else if trace {
+                let tracer = Tracer::new(config);
+                observe(Rc::new(RefCell::new(tracer)), || loader.run(path)).unwrap_or_else(exit);
+            } else if let Some(output) = profile {
+                let profiler = Rc::new(RefCell::new(Profiler::new(config)));
+                let result = batch::catch(|| observe(profiler.clone(), || loader.run(path)));
+
+                let mut profiler = profiler.borrow_mut();
+                profiler.finish();
 
-            let ast = translate::parse(&mut tokens, &config);
-            let new_file_contents = pack(&ast);
+                Stream::File(PathBuf::from(output))
+                    .write(&profiler.folded())
+                    .unwrap_or_else(exit);
 
-            // Write file to output
-            let mut file = File::create(output).unwrap();
-            file.write_all(new_file_contents.as_bytes()).unwrap();
+                for (function, calls) in profiler.functions() {
+                    info!(
+                        "{:>8} calls {:>12.3?}  {}",
+                        calls.count, calls.time, function
+                    );
+                }
+
+                result.unwrap_or_else(fail);
+            } else {
+                loader.run(path).unwrap_or_else(exit);
+            }
         }
-        Commands::Unpack { input, output } => {
-            let mut file = File::open(input).unwrap();
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
+        Commands::Translate {
+            from,
+            to,
+            input,
+            output,
+        } => {
+            let from = Locale::find(&from, config).unwrap_or_else(exit);
+            let to = Locale::find(&to, config).unwrap_or_else(exit);
+            let (from, to) = (from.apply(config), to.apply(config));
+
+            let (input, output) = (Path::new(&input), Path::new(&output));
+            let files = match input.is_dir() {
+                true => batch::files(input, SCRIPT_EXTENSION)
+                    .into_iter()
+                    .map(|file| (input.join(&file), output.join(&file)))
+                    .collect(),
+                false => vec![(input.to_path_buf(), output.to_path_buf())],
+            };
+
+            let mut collisions = false;
 
-            let unpacked = unpack(contents, &config);
+            for (input, output) in files {
+                collisions |= translate_file(&input, &output, &from, &to);
+            }
 
-            let mut file = File::create(output).unwrap();
-            file.write_all(unpacked.as_bytes()).unwrap();
+            if collisions {
+                process::exit(1);
+            }
         }
-        Commands::Run { file } => {
-            let mut file = File::open(file).unwrap();
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
+        Commands::Watch {
+            directory,
+            interval,
+        } => {
+            Watcher::new(Path::new(&directory), config).run(Duration::from_millis(interval));
+        }
+        Commands::Bench { file, iterations } => {
+            let input = Stream::File(PathBuf::from(file));
+            let contents = input.read().unwrap_or_else(exit);
+
+            // Scripts with text keywords are packed first, otherwise the tree
+            // walker warns about every keyword it runs into and the timings
+            // are mostly the warnings
+            let contents = match is_packed(&contents) {
+                true => contents,
+                false => batch::pack_script(&input, contents, config, false).unwrap_or_else(exit),
+            };
 
             let tokenizer = translate::Tokenizer::from_string(contents);
             let mut tokens = tokenizer.tokenize();
 
-            let ast = translate::parse(&mut tokens, &config);
-            let mut env = Environment::new();
+            let mut ast = translate::parse(&mut tokens, config);
+            resolve(
+                &mut ast,
+                &stdlib::globals(config, stdlib::stdout(), Capabilities::all()),
+            )
+            .unwrap_or_else(exit);
 
-            env.add_rust_function("print", vec![String::from("value")], |args, _env| {
-                println!("{}", args[0]);
-                Value::Option(None)
+            let tree_walker = time(iterations, || {
+                let _ = execute(
+                    &ast,
+                    stdlib::globals(config, stdlib::stdout(), Capabilities::all()).contain(),
+                );
             });
 
-            let env = env.contain();
+            let start = Instant::now();
+            let program = vm::compile(&ast);
+            let compile_time = start.elapsed();
+
+            let virtual_machine = time(iterations, || {
+                vm::run(
+                    &program,
+                    stdlib::globals(config, stdlib::stdout(), Capabilities::all()).contain(),
+                );
+            });
+
+            println!("tree walker:     {:?} / run", tree_walker);
+            println!(
+                "virtual machine: {:?} / run (+ {:?} to compile)",
+                virtual_machine, compile_time
+            );
+            println!(
+                "speedup:         {:.2}x",
+                tree_walker.as_secs_f64() / virtual_machine.as_secs_f64()
+            );
+        }
+        // Checking the config does not need a config that works
+        Commands::Config { .. } => unreachable!(),
+    }
+}
 
-            execute(&ast, env);
-            // println!("{:#?}", ast);
+/// Reports the config files that were found and any problems with them, and
+/// exits
+fn check_config(config: Result<Config, Error>) -> ! {
+    match config {
+        Ok(config) if config.files.is_empty() => {
+            info!("No config files found, so the defaults are used");
         }
+        Ok(config) => {
+            for file in &config.files {
+                info!("Read {}", file.display());
+            }
+            info!("No problems found");
+        }
+        Err(Error::InvalidConfig(problems)) => {
+            for problem in &problems {
+                error!("{}", problem);
+            }
+            process::exit(1);
+        }
+        Err(error) => exit(error),
     }
+
+    process::exit(0);
+}
+
+/// Translates one script, returning whether it defines names that are keywords
+/// or builtins in the locale it was translated to
+fn translate_file(input: &Path, output: &Path, from: &Config, to: &Config) -> bool {
+    let contents = fs::read_to_string(input).unwrap_or_else(|error| {
+        exit(Error::File {
+            path: input.to_path_buf(),
+            error,
+        })
+    });
+    let tokens = translate::Tokenizer::from_file(input, contents).tokenize();
+
+    let conversion = convert(tokens, from, to).unwrap_or_else(exit);
+
+    for token in &conversion.collisions {
+        warn_token(
+            token,
+            &format!(
+                "This name would be read as a keyword or builtin in {}, rename it before translating",
+                to.language.as_deref().unwrap_or_default()
+            ),
+        );
+    }
+
+    Stream::File(output.to_path_buf())
+        .write(&conversion.source)
+        .unwrap_or_else(exit);
+
+    !conversion.collisions.is_empty()
 }
 
-fn setup_logger() -> Result<(), fern::InitError> {
+/// The scripts that `pack` or `unpack` reads and where each is written,
+/// exiting if the arguments do not fit together
+fn jobs(
+    input: &str,
+    output: Option<String>,
+    in_place: bool,
+    source_map: bool,
+    from: &str,
+    to: &str,
+) -> Vec<Job> {
+    let jobs =
+        batch::jobs(input, output.as_deref(), in_place, from, to).unwrap_or_else(|message| {
+            error!("{}\n", message);
+            process::exit(2);
+        });
+
+    let streams = jobs
+        .iter()
+        .any(|job| job.input.path().is_none() || job.output.path().is_none());
+    if source_map && streams {
+        error!("Source maps are written next to the output and point at the input, so --source-map needs files for both\n");
+        process::exit(2);
+    }
+
+    jobs
+}
+
+/// Reports an error from the library, against the part of the script that
+/// caused it if there is one, and exits
+fn exit<T>(error: Error) -> T {
+    match error {
+        Error::Resolve(errors) => {
+            for error in errors {
+                error_token(&error.token, &error.message);
+            }
+        }
+        Error::Script { token, message } => error_token(&token, &message),
+        error => error!("{}\n", error),
+    }
+
+    process::exit(1);
+}
+
+/// Reports a script that could not be parsed or run, and exits
+fn fail<T>(failure: Failure) -> T {
+    match failure {
+        Failure::Parse(error) => {
+            error_token_with_help(&error.token, &error.message, &error.help);
+            process::exit(1);
+        }
+        Failure::Error(error) => exit(error),
+    }
+}
+
+/// Returns the mean time taken by `run` over the given number of iterations
+fn time(iterations: u32, mut run: impl FnMut()) -> Duration {
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        run();
+    }
+
+    start.elapsed() / iterations
+}
+
+fn setup_logger(stderr: bool) -> Result<(), fern::InitError> {
     let colors = ColoredLevelConfig::new()
         .info(Color::Blue)
         .warn(Color::Yellow);
 
-    Dispatch::new()
+    let dispatch = Dispatch::new()
         .format(move |out, message, record| {
             out.finish(format_args!("{} {}", colors.color(record.level()), message))
         })
-        .level(log::LevelFilter::Debug)
-        .chain(std::io::stdout())
-        .apply()?;
+        .level(log::LevelFilter::Debug);
+
+    match stderr {
+        true => dispatch.chain(std::io::stderr()),
+        false => dispatch.chain(std::io::stdout()),
+    }
+    .apply()?;
 
     Ok(())
 }
@@ -0,0 +1,279 @@
+//! Observers that time scripts run by the tree walker: `run --trace` logs every
+//! statement and function call as it happens, and `run --profile` sums up where
+//! the time went once the script has finished.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use transcript::{
+    executor::{Environment, Observer},
+    translate::AST,
+    Config,
+};
+
+use crate::debug::Renderer;
+
+/// Logs each statement before it runs and each function call once it returns,
+/// with the time since the script started
+pub struct Tracer {
+    renderer: Renderer,
+    start: Instant,
+    /// When each function that is running was called, innermost last
+    calls: Vec<(String, Instant)>,
+}
+
+impl Tracer {
+    pub fn new(config: &Config) -> Tracer {
+        Tracer {
+            renderer: Renderer::new(config),
+            start: Instant::now(),
+            calls: Vec::new(),
+        }
+    }
+
+    fn log(&self, message: String) {
+        debug!(
+            "{:>10.3?} {}{}",
+            self.start.elapsed(),
+            "  ".repeat(self.calls.len()),
+            message
+        );
+    }
+}
+
+impl Observer for Tracer {
+    fn statement(&mut self, statement: &AST, _env: &Rc<RefCell<Environment>>) {
+        if let Some(token) = statement.token() {
+            self.log(format!(
+                "{} | {}",
+                token.line,
+                self.renderer.source(token.line_contents.trim())
+            ));
+        }
+    }
+
+    fn enter(&mut self, function: &str) {
+        let name = self.renderer.name(function);
+        self.log(format!("call {}", name));
+        self.calls.push((name, Instant::now()));
+    }
+
+    fn exit(&mut self) {
+        if let Some((name, start)) = self.calls.pop() {
+            self.log(format!("return {} after {:.3?}", name, start.elapsed()));
+        }
+    }
+}
+
+/// How often a function was called and how long it ran for
+#[derive(Default)]
+pub struct Calls {
+    pub count: u64,
+    /// The time from calling the function to it returning. Recursive calls are
+    /// only counted once, as part of the outermost call
+    pub time: Duration,
+}
+
+struct Call {
+    name: String,
+    start: Instant,
+    /// The time spent in functions that this call called
+    children: Duration,
+}
+
+/// Records how long every function call takes, by the chain of calls that led
+/// to it
+pub struct Profiler {
+    renderer: Renderer,
+    stack: Vec<Call>,
+    /// Time spent in the innermost function of each stack, not counting the
+    /// functions it called
+    stacks: BTreeMap<String, Duration>,
+    functions: BTreeMap<String, Calls>,
+}
+
+impl Profiler {
+    pub fn new(config: &Config) -> Profiler {
+        let script = String::from("<script>");
+        let mut functions = BTreeMap::new();
+        functions.insert(
+            script.clone(),
+            Calls {
+                count: 1,
+                ..Default::default()
+            },
+        );
+
+        Profiler {
+            renderer: Renderer::new(config),
+            stack: vec![Call {
+                name: script,
+                start: Instant::now(),
+                children: Duration::ZERO,
+            }],
+            stacks: BTreeMap::new(),
+            functions,
+        }
+    }
+
+    /// Stops timing the script itself. Functions that are still running, e.g.
+    /// because the script stopped with an error, are counted up to now.
+    pub fn finish(&mut self) {
+        while !self.stack.is_empty() {
+            self.exit();
+        }
+    }
+
+    /// Each stack on its own line, as the names of the functions separated by
+    /// semicolons followed by the microseconds spent there. This is the folded
+    /// format that flamegraph tools take.
+    pub fn folded(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(stack, time)| format!("{} {}\n", stack, time.as_micros()))
+            .collect()
+    }
+
+    pub fn functions(&self) -> &BTreeMap<String, Calls> {
+        &self.functions
+    }
+}
+
+impl Observer for Profiler {
+    fn enter(&mut self, function: &str) {
+        let name = self.renderer.name(function);
+        self.functions.entry(name.clone()).or_default().count += 1;
+
+        self.stack.push(Call {
+            name,
+            start: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    fn exit(&mut self) {
+        let stack = self
+            .stack
+            .iter()
+            .map(|call| call.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(";");
+
+        let call = match self.stack.pop() {
+            Some(call) => call,
+            None => return,
+        };
+        let elapsed = call.start.elapsed();
+
+        *self.stacks.entry(stack).or_default() += elapsed.saturating_sub(call.children);
+
+        if let Some(caller) = self.stack.last_mut() {
+            caller.children += elapsed;
+        }
+
+        if !self.stack.iter().any(|outer| outer.name == call.name) {
+            self.functions.entry(call.name).or_default().time += elapsed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use transcript::{executor::observe, module::Loader};
+
+    use super::*;
+
+    /// Runs a script under a profiler, returning the profiler once the script
+    /// has finished
+    fn profile(test: &str, source: &str) -> Profiler {
+        let directory =
+            std::env::temp_dir().join(format!("transcript-{}-{}", test, std::process::id()));
+        let script = directory.join("script.script");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&script, source).unwrap();
+
+        let config = Config::default();
+        let profiler = Rc::new(RefCell::new(Profiler::new(&config)));
+        let mut loader = Loader::new(&config, false);
+        loader.set_output(Rc::new(RefCell::new(Box::new(std::io::sink()))));
+        observe(profiler.clone(), || loader.run(&script)).unwrap();
+
+        fs::remove_dir_all(directory).unwrap();
+        let mut profiler = Rc::try_unwrap(profiler).ok().unwrap().into_inner();
+        profiler.finish();
+        profiler
+    }
+
+    fn counts(profiler: &Profiler) -> Vec<(&str, u64)> {
+        profiler
+            .functions()
+            .iter()
+            .map(|(function, calls)| (function.as_str(), calls.count))
+            .collect()
+    }
+
+    fn stacks(profiler: &Profiler) -> Vec<String> {
+        profiler
+            .folded()
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn calls_are_counted_against_the_function_that_was_called() {
+        let profiler = profile(
+            "profile-calls",
+            "function inner(n) {\n    return n + 1;\n};\nfunction outer(n) {\n    return inner(inner(n));\n};\nprint(outer(1));\nprint(inner(1));\n",
+        );
+
+        assert_eq!(
+            counts(&profiler),
+            [("<script>", 1), ("inner", 3), ("outer", 1), ("print", 2)]
+        );
+        assert_eq!(
+            stacks(&profiler),
+            [
+                "<script>",
+                "<script>;inner",
+                "<script>;outer",
+                "<script>;outer;inner",
+                "<script>;print",
+            ]
+        );
+    }
+
+    #[test]
+    fn recursive_calls_are_timed_once() {
+        let profiler = profile(
+            "profile-recursion",
+            "function count(n) {\n    return map(range(n), count);\n};\ncount(3);\n",
+        );
+        let count = &profiler.functions()["count"];
+        let folded: Duration = profiler
+            .folded()
+            .lines()
+            .filter(|line| line.starts_with("<script>;count"))
+            .map(|line| Duration::from_micros(line.rsplit_once(' ').unwrap().1.parse().unwrap()))
+            .sum();
+
+        assert_eq!(
+            counts(&profiler),
+            [("<script>", 1), ("count", 8), ("map", 8), ("range", 8)]
+        );
+        assert!(stacks(&profiler).contains(&String::from(
+            "<script>;count;map;count;map;count;map;count"
+        )));
+        // The time of the outermost call is all of the time spent in count,
+        // rather than the sum of every nested call
+        assert!(count.time >= folded);
+        assert!(count.time <= profiler.functions()["<script>"].time);
+    }
+}
@@ -1,5 +0,0 @@
-mod config;
-mod logging;
-
-pub use config::*;
-pub use logging::*;
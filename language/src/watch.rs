@@ -0,0 +1,247 @@
+//! Keeps the scripts in a directory and their packed copies in sync. When a
+//! script is edited it is packed again, and when a packed file changes (e.g.
+//! after pulling) it is unpacked again in the language of the reader.
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs, panic,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use log::{error, info, warn};
+use transcript::{file::unpack, utils::Config};
+
+use crate::{
+    batch::{catch, files, pack_script, report, Stream},
+    PACKED_EXTENSION, SCRIPT_EXTENSION,
+};
+
+/// Which side of a pair of files to sync the other side from
+#[derive(Clone, Copy)]
+enum Side {
+    Script,
+    Packed,
+    Neither,
+}
+
+pub struct Watcher<'a> {
+    directory: PathBuf,
+    config: &'a Config,
+    /// When each file was last seen to change, including changes made by the
+    /// watcher itself
+    seen: HashMap<PathBuf, SystemTime>,
+}
+
+impl<'a> Watcher<'a> {
+    pub fn new(directory: &Path, config: &'a Config) -> Watcher<'a> {
+        Watcher {
+            directory: directory.to_path_buf(),
+            config,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Checks for changes every `interval`, forever
+    pub fn run(&mut self, interval: Duration) -> ! {
+        // Scripts that fail to parse are reported by `sync`, rather than
+        // stopping the watcher
+        panic::set_hook(Box::new(|_| ()));
+
+        info!("Watching {} for changes", self.directory.display());
+
+        loop {
+            self.check();
+            thread::sleep(interval);
+        }
+    }
+
+    /// Syncs every pair of files where one side changed since it was last
+    /// seen. On the first check, every pair where one side is missing or older
+    /// than the other is synced.
+    fn check(&mut self) {
+        let first = self.seen.is_empty();
+
+        let mut pairs: Vec<PathBuf> = files(&self.directory, SCRIPT_EXTENSION)
+            .into_iter()
+            .chain(files(&self.directory, PACKED_EXTENSION))
+            .map(|file| self.directory.join(file).with_extension(""))
+            .collect();
+        pairs.sort();
+        pairs.dedup();
+
+        for pair in pairs {
+            let script = pair.with_extension(SCRIPT_EXTENSION);
+            let packed = pair.with_extension(PACKED_EXTENSION);
+
+            let changed = |path: &Path| {
+                modified(path).is_some() && modified(path) != self.seen.get(path).copied()
+            };
+
+            let side = match (changed(&script), changed(&packed)) {
+                (false, false) => continue,
+                (true, false) => Side::Script,
+                (false, true) => Side::Packed,
+                (true, true) if first => match modified(&script).cmp(&modified(&packed)) {
+                    Ordering::Greater => Side::Script,
+                    Ordering::Less => Side::Packed,
+                    Ordering::Equal => Side::Neither,
+                },
+                (true, true) => {
+                    warn!(
+                        "Both {} and {} changed, so neither is overwritten",
+                        script.display(),
+                        packed.display()
+                    );
+                    Side::Neither
+                }
+            };
+
+            match side {
+                Side::Script => self.sync(&script, &packed, side),
+                Side::Packed => self.sync(&packed, &script, side),
+                Side::Neither => (),
+            }
+
+            self.remember(&script);
+            self.remember(&packed);
+        }
+    }
+
+    fn remember(&mut self, path: &Path) {
+        if let Some(modified) = modified(path) {
+            self.seen.insert(path.to_path_buf(), modified);
+        }
+    }
+
+    /// Writes `to` from `from`, unless `from` cannot be read or `to` would not
+    /// change
+    fn sync(&self, from: &Path, to: &Path, side: Side) {
+        let contents = match fs::read_to_string(from) {
+            Ok(contents) => contents,
+            Err(error) => return error!("{}: {}\n", from.display(), error),
+        };
+
+        let result = match side {
+            Side::Script => catch(|| {
+                pack_script(
+                    &Stream::File(from.to_path_buf()),
+                    contents,
+                    self.config,
+                    false,
+                )
+            }),
+            _ => catch(|| unpack(contents, self.config)),
+        };
+
+        let output = match result {
+            Ok(output) => output,
+            Err(failure) => return report(&from.display(), failure),
+        };
+
+        if fs::read_to_string(to).ok().as_ref() == Some(&output) {
+            return;
+        }
+
+        match fs::write(to, output) {
+            Ok(()) => info!("{} -> {}", from.display(), to.display()),
+            Err(error) => error!("{}: {}\n", to.display(), error),
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    /// Writes a file that was last modified `seconds` after the epoch, so that
+    /// which side of a pair is newer does not depend on how fast tests run
+    fn write(path: &Path, contents: &str, seconds: u64) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    fn directory(test: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("transcript-{}-{}", test, std::process::id()))
+    }
+
+    fn packed(source: &str, config: &Config) -> String {
+        pack_script(&Stream::Stdin, source.to_string(), config, false).unwrap()
+    }
+
+    #[test]
+    fn the_first_check_syncs_from_whichever_side_is_newer() {
+        let directory = directory("watch-first");
+        let config = Config::default();
+
+        write(&directory.join("new.script"), "print(1);", 100);
+        write(
+            &directory.join("pulled.packed"),
+            &packed("print(2);", &config),
+            100,
+        );
+        write(&directory.join("edited.script"), "print(3);", 200);
+        write(
+            &directory.join("edited.packed"),
+            &packed("print(4);", &config),
+            100,
+        );
+
+        Watcher::new(&directory, &config).check();
+
+        let read = |name: &str| fs::read_to_string(directory.join(name)).unwrap();
+        assert_eq!(read("new.packed"), packed("print(1);", &config));
+        assert_eq!(read("pulled.script"), "print(2);");
+        assert_eq!(read("edited.packed"), packed("print(3);", &config));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn later_checks_only_sync_what_changed() {
+        let directory = directory("watch-later");
+        let config = Config::default();
+        let script = directory.join("a.script");
+        let packed_file = directory.join("a.packed");
+        let read = || fs::read_to_string(&packed_file).unwrap();
+
+        write(&script, "print(1);", 100);
+
+        let mut watcher = Watcher::new(&directory, &config);
+        watcher.check();
+        assert_eq!(read(), packed("print(1);", &config));
+
+        write(&script, "print(2);", 200);
+        watcher.check();
+        assert_eq!(read(), packed("print(2);", &config));
+
+        // Scripts that do not parse are reported, and the packed copy is kept
+        write(&script, "let a = (1;", 300);
+        watcher.check();
+        assert_eq!(read(), packed("print(2);", &config));
+
+        write(&script, "print(3);", 400);
+        write(&packed_file, &packed("print(4);", &config), 400);
+        watcher.check();
+        assert_eq!(fs::read_to_string(&script).unwrap(), "print(3);");
+        assert_eq!(read(), packed("print(4);", &config));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
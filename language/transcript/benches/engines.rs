@@ -0,0 +1,56 @@
+//! Times the tree walking interpreter against the virtual machine on the
+//! scripts in this directory. The scripts are packed before they are timed,
+//! like the scripts that people share, so that neither engine warns about text
+//! keywords while it runs.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use transcript::{
+    executor::{execute, Environment},
+    file::pack,
+    module::resolve,
+    stdlib::{self, Capabilities},
+    translate::{self, Tokenizer, AST},
+    vm, Config,
+};
+
+const SCRIPTS: [(&str, &str); 2] = [
+    ("arithmetic", include_str!("arithmetic.script")),
+    ("calls", include_str!("calls.script")),
+];
+
+fn globals(config: &Config) -> Environment {
+    stdlib::globals(config, stdlib::stdout(), Capabilities::all())
+}
+
+/// Packs a script and parses the packed copy
+fn packed(source: &str, config: &Config) -> AST {
+    let mut tokens = Tokenizer::from_string(source.to_string()).tokenize();
+    let packed = pack(&translate::parse(&mut tokens, config), config);
+
+    let mut tokens = Tokenizer::from_string(packed).tokenize();
+    let mut ast = translate::parse(&mut tokens, config);
+    resolve(&mut ast, &globals(config)).unwrap();
+
+    ast
+}
+
+fn engines(c: &mut Criterion) {
+    let config = Config::default();
+
+    for (name, source) in SCRIPTS {
+        let ast = packed(source, &config);
+        let program = vm::compile(&ast);
+
+        let mut group = c.benchmark_group(name);
+        group.bench_function("tree walker", |b| {
+            b.iter(|| black_box(execute(&ast, globals(&config).contain()).is_ok()))
+        });
+        group.bench_function("virtual machine", |b| {
+            b.iter(|| black_box(vm::run(&program, globals(&config).contain())))
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, engines);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+//! Runs a script from rust, sharing functions in both directions.
+//!
+//! cargo run --example embed
+//!
+//! Expected output:
+//! captured: hello from the script
+//! area: 12
+//! doubled: [2, 4, 6]
+
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use transcript::{Error, Interpreter, IntoValue};
+
+/// Collects everything a script prints, so the host can decide what to do
+/// with it
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let captured = Captured::default();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(captured.clone());
+    interpreter.register("area", |width: f32, height: f32| width * height);
+    interpreter.register("double_all", |numbers: Vec<f32>| {
+        numbers
+            .into_iter()
+            .map(|number| number * 2.0)
+            .collect::<Vec<_>>()
+    });
+
+    interpreter.eval_str(
+        "print(\"hello from the script\");
+        function box_area(side) { return area(side, side + 1); };",
+    )?;
+
+    print!(
+        "captured: {}",
+        String::from_utf8_lossy(&captured.0.borrow())
+    );
+
+    let area = interpreter.call_function("box_area", vec![3.0.into_value()])?;
+    println!("area: {}", area);
+
+    let doubled = interpreter.eval_str("double_all([1, 2, 3]);")?;
+    println!("doubled: {}", doubled);
+
+    Ok(())
+}
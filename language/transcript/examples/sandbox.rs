@@ -0,0 +1,49 @@
+//! Runs scripts that cannot be trusted, with limits on how much work they can
+//! do and without the builtins that reach outside of the script.
+//!
+//! cargo run --example sandbox
+//!
+//! Expected output:
+//! runaway: line 1: Script exceeded its limit of 5000 steps
+//! recursion: line 1: Script exceeded its limit of 100 nested calls
+//! memory: line 1: Script exceeded its limit of 65536 bytes
+//! files: line 1: Undefined variable: read_file
+//! still usable: 3
+
+use std::time::Duration;
+
+use transcript::{executor::Limits, Interpreter};
+
+fn main() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_limits(Limits {
+        steps: Some(5_000),
+        call_depth: Some(100),
+        memory: Some(64 * 1024),
+        time: Some(Duration::from_secs(1)),
+    });
+
+    let scripts = [
+        (
+            "runaway",
+            "map(range(0, 500), function(n) { return n * n * n + n * n + n; });",
+        ),
+        (
+            "recursion",
+            "function down(n) { return down(n + 1) + 1; }; down(0);",
+        ),
+        (
+            "memory",
+            "function grow(s) { return grow(s + s); }; grow(\"data\");",
+        ),
+        ("files", "read_file(\"/etc/passwd\");"),
+        ("still usable", "1 + 2;"),
+    ];
+
+    for (name, script) in scripts {
+        match interpreter.eval_str(script) {
+            Ok(value) => println!("{}: {}", name, value),
+            Err(error) => println!("{}: {}", name, error),
+        }
+    }
+}
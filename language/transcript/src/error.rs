@@ -0,0 +1,80 @@
+use std::{fmt::Display, io, path::PathBuf};
+
+use crate::{
+    executor::Limit,
+    translate::{ResolveError, Token},
+    utils::Problem,
+};
+
+/// Everything that can go wrong while loading, translating or running a script
+#[derive(Debug)]
+pub enum Error {
+    /// The script could not be parsed
+    Parse(String),
+    /// The script uses names that do not exist, or defines them twice
+    Resolve(Vec<ResolveError>),
+    /// A problem with a specific part of a script, e.g. an import that cannot
+    /// be found
+    Script { token: Token, message: String },
+    /// The script failed while it was running
+    Runtime {
+        message: String,
+        line: Option<usize>,
+    },
+    /// The script used more than the `Limits` it was given, on the line of
+    /// the call that was running at the time
+    Limit { limit: Limit, line: Option<usize> },
+    /// A value could not be converted to the rust type that was asked for
+    Conversion(String),
+    /// The configuration cannot be used, e.g. a glossary that translates two
+    /// names to the same thing
+    Config(String),
+    /// The config sets keywords or builtin names that scripts could not use
+    InvalidConfig(Vec<Problem>),
+    /// A script could not be read or written, e.g. because it does not exist
+    File { path: PathBuf, error: io::Error },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parse(message) => write!(f, "Syntax error: {}", message),
+            Error::Resolve(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i != 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "line {}: {}", error.token.line, error.message)?;
+                }
+                Ok(())
+            }
+            Error::Script { token, message } => write!(f, "line {}: {}", token.line, message),
+            Error::Runtime {
+                message,
+                line: Some(line),
+            } => write!(f, "line {}: {}", line, message),
+            Error::Runtime {
+                message,
+                line: None,
+            } => write!(f, "{}", message),
+            Error::InvalidConfig(problems) => {
+                for (i, problem) in problems.iter().enumerate() {
+                    if i != 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", problem)?;
+                }
+                Ok(())
+            }
+            Error::Limit {
+                limit,
+                line: Some(line),
+            } => write!(f, "line {}: {}", line, limit),
+            Error::Limit { limit, line: None } => write!(f, "{}", limit),
+            Error::File { path, error } => write!(f, "{}: {}", path.display(), error),
+            Error::Conversion(message) | Error::Config(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
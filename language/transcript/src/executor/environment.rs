@@ -0,0 +1,158 @@
+use std::{cell::RefCell, rc::Rc};
+
+use super::{Arity, Call, Value};
+
+/// A single scope. Variables are stored in slots that are assigned by the
+/// resolver, the names are only kept around for lookups from rust code (e.g.
+/// the virtual machine picking up builtins) and for seeding the resolver.
+pub struct Environment {
+    names: Vec<String>,
+    values: Vec<Option<Value>>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment {
+            names: Vec::new(),
+            values: Vec::new(),
+            enclosing: None,
+        }
+    }
+
+    pub fn from_enclosing(enclosing: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            names: Vec::new(),
+            values: Vec::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /// Defines a variable by name, reusing its slot if it already exists.
+    pub fn define(&mut self, name: &str, value: Value) {
+        let slot = self
+            .names
+            .iter()
+            .position(|existing| existing == name)
+            .unwrap_or(self.names.len());
+
+        self.define_slot(slot, name, value);
+    }
+
+    pub fn define_slot(&mut self, slot: usize, name: &str, value: Value) {
+        if slot >= self.values.len() {
+            self.names.resize(slot + 1, String::new());
+            self.values.resize(slot + 1, None);
+        }
+
+        self.names[slot] = name.to_string();
+        self.values[slot] = Some(value);
+    }
+
+    /// Returns `None` if the slot has been resolved, but the definition has
+    /// not been executed yet.
+    pub fn get_slot(&self, depth: usize, slot: usize) -> Option<Value> {
+        if depth == 0 {
+            return self.values.get(slot).cloned().flatten();
+        }
+
+        self.enclosing
+            .as_ref()
+            .expect("Resolved depth is deeper than the environment chain")
+            .borrow()
+            .get_slot(depth - 1, slot)
+    }
+
+    /// Returns `false` if the variable has not been defined yet
+    pub fn assign_slot(&mut self, depth: usize, slot: usize, value: Value) -> bool {
+        if depth == 0 {
+            return match self.values.get_mut(slot) {
+                Some(existing @ Some(_)) => {
+                    *existing = Some(value);
+                    true
+                }
+                _ => false,
+            };
+        }
+
+        self.enclosing
+            .as_ref()
+            .expect("Resolved depth is deeper than the environment chain")
+            .borrow_mut()
+            .assign_slot(depth - 1, slot, value)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        if let Some(slot) = self.names.iter().position(|existing| existing == name) {
+            if let Some(value) = &self.values[slot] {
+                return Some(value.clone());
+            }
+        }
+
+        if let Some(ref env) = self.enclosing {
+            return env.borrow().get(name);
+        }
+
+        None
+    }
+
+    /// The names of the variables in this scope, indexed by slot
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The names of the variables in this scope and every scope around it,
+    /// outermost first, for seeding the resolver
+    pub fn scopes(&self) -> Vec<Vec<String>> {
+        let mut scopes = match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().scopes(),
+            None => Vec::new(),
+        };
+
+        scopes.push(self.names.clone());
+        scopes
+    }
+
+    /// The variables in this scope that have been defined, in slot order
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        self.names
+            .iter()
+            .zip(&self.values)
+            .filter_map(|(name, value)| Some((name.to_string(), value.clone()?)))
+            .collect()
+    }
+
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    pub fn contain(self) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(self))
+    }
+
+    /// Defines a function that is implemented in rust. `args` are the names of
+    /// the arguments, used when the function is printed
+    pub fn add_rust_function(
+        &mut self,
+        name: &str,
+        args: &[&str],
+        arity: Arity,
+        body: impl Fn(Vec<Value>, &mut dyn Call) -> Value + 'static,
+    ) {
+        self.define(
+            name,
+            Value::RustFunction {
+                name: name.to_string(),
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+                arity,
+                body: Rc::new(body),
+            },
+        );
+    }
+}
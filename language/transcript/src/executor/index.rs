@@ -0,0 +1,117 @@
+use crate::translate::Token;
+
+use super::{limits, runtime_error, Value};
+
+/// Converts a script index into an index into `length` items. Negative indexes
+/// count back from the end of the list, so `-1` is the last item.
+fn to_index(index: &Value, length: usize, token: &Token) -> isize {
+    let index = match index {
+        Value::Number(index) if index.fract() == 0.0 => *index as isize,
+        _ => runtime_error(
+            token,
+            &format!("List indexes must be whole numbers, got {}", index),
+        ),
+    };
+
+    if index < 0 {
+        index + length as isize
+    } else {
+        index
+    }
+}
+
+fn checked_index(index: &Value, length: usize, token: &Token) -> usize {
+    let checked = to_index(index, length, token);
+
+    if checked < 0 || checked >= length as isize {
+        runtime_error(
+            token,
+            &format!(
+                "Index {} is out of bounds for a list of length {}",
+                index, length
+            ),
+        );
+    }
+
+    checked as usize
+}
+
+fn key<'a>(key: &'a Value, token: &Token) -> &'a str {
+    match key {
+        Value::String(key) => key,
+        _ => runtime_error(token, &format!("Map keys must be strings, got {}", key)),
+    }
+}
+
+/// Looks up `list[index]` or `map[key]`. `token` covers the index expression,
+/// so that errors point at it.
+pub fn get_index(collection: &Value, index: &Value, token: &Token) -> Value {
+    match collection {
+        Value::List(items) => {
+            let items = items.borrow();
+            items[checked_index(index, items.len(), token)].clone()
+        }
+        Value::Map(entries) => {
+            let key = key(index, token);
+
+            match entries
+                .borrow()
+                .iter()
+                .find(|(existing, _)| existing == key)
+            {
+                Some((_, value)) => value.clone(),
+                None => runtime_error(token, &format!("\"{}\" is not in the map", key)),
+            }
+        }
+        _ => runtime_error(token, &format!("Cannot index into {}", collection)),
+    }
+}
+
+/// Sets `list[index]` or `map[key]`. New keys are added to the end of the map
+pub fn set_index(collection: &Value, index: &Value, value: Value, token: &Token) {
+    match collection {
+        Value::List(items) => {
+            let mut items = items.borrow_mut();
+            let index = checked_index(index, items.len(), token);
+            items[index] = value;
+        }
+        Value::Map(entries) => {
+            let key = key(index, token);
+            let mut entries = entries.borrow_mut();
+
+            match entries.iter_mut().find(|(existing, _)| existing == key) {
+                Some((_, existing)) => *existing = value,
+                None => {
+                    limits::allocate(key.len() + std::mem::size_of::<(String, Value)>());
+                    entries.push((key.to_string(), value));
+                }
+            }
+        }
+        _ => runtime_error(token, &format!("Cannot index into {}", collection)),
+    }
+}
+
+/// Copies `list[start:end]` into a new list. A bound of none means the start
+/// or end of the list. Unlike indexing, bounds that are out of range are moved
+/// back into the list rather than being an error.
+pub fn slice(list: &Value, start: &Value, end: &Value, token: &Token) -> Value {
+    let items = match list {
+        Value::List(items) => items.borrow(),
+        _ => runtime_error(token, &format!("Cannot slice {}", list)),
+    };
+
+    let length = items.len();
+    let bound = |bound: &Value, default: usize| match bound {
+        Value::Option(None) => default,
+        bound => to_index(bound, length, token).clamp(0, length as isize) as usize,
+    };
+
+    let start = bound(start, 0);
+    let end = bound(end, length);
+
+    if start >= end {
+        return Value::list(Vec::new());
+    }
+
+    Value::list(items[start..end].to_vec())
+}
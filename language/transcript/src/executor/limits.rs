@@ -0,0 +1,294 @@
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    time::{Duration, Instant},
+};
+
+use super::{caller, Value};
+use crate::translate::Token;
+
+/// How deep scripts can recurse unless the host says otherwise
+pub const DEFAULT_CALL_DEPTH: usize = 1000;
+
+/// How often the clock is read, in steps. Reading it on every step would slow
+/// every script down to catch the few that run for too long
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// Bounds on how much work a script can do, for running scripts that cannot be
+/// trusted. `None` means there is no limit.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// The number of expressions or instructions that can be evaluated
+    pub steps: Option<u64>,
+    /// How many function calls can be on the stack at once
+    pub call_depth: Option<usize>,
+    /// The total number of bytes that can be allocated for strings, lists and
+    /// maps. Memory that is freed again is not given back.
+    pub memory: Option<usize>,
+    /// How long a script can run for
+    pub time: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            steps: None,
+            call_depth: Some(DEFAULT_CALL_DEPTH),
+            memory: None,
+            time: None,
+        }
+    }
+}
+
+impl Limits {
+    /// No limits at all, for scripts that are trusted
+    pub fn none() -> Limits {
+        Limits {
+            steps: None,
+            call_depth: None,
+            memory: None,
+            time: None,
+        }
+    }
+}
+
+/// The limit that stopped a script
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    Steps(u64),
+    CallDepth(usize),
+    Memory(usize),
+    Time(Duration),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Steps(steps) => write!(f, "Script exceeded its limit of {} steps", steps),
+            Limit::CallDepth(depth) => {
+                write!(f, "Script exceeded its limit of {} nested calls", depth)
+            }
+            Limit::Memory(bytes) => write!(f, "Script exceeded its limit of {} bytes", bytes),
+            Limit::Time(time) => write!(f, "Script exceeded its time limit of {:?}", time),
+        }
+    }
+}
+
+/// A limit that a script exceeded, along with the innermost call that was
+/// running when it did. Scripts that exceed a limit unwind with this as the
+/// payload, without running the panic hook, and `Interpreter` turns it into
+/// `Error::Limit`.
+#[derive(Clone, Debug)]
+pub struct LimitError {
+    pub limit: Limit,
+    /// Scripts that exceed a limit outside of any call have none
+    pub token: Option<Token>,
+}
+
+/// What the script that is currently running has used so far
+struct Budget {
+    limits: Limits,
+    steps: u64,
+    depth: usize,
+    memory: usize,
+    deadline: Option<Instant>,
+}
+
+thread_local! {
+    static BUDGET: RefCell<Option<Budget>> = const { RefCell::new(None) };
+}
+
+fn exceeded(limit: Limit) -> ! {
+    panic::resume_unwind(Box::new(LimitError {
+        limit,
+        token: caller(),
+    }))
+}
+
+/// Runs `run` with `limits` enforced. Anything that `run` executes shares one
+/// budget, so a script and every module it imports are limited together.
+pub fn enforce<T>(limits: Limits, run: impl FnOnce() -> T) -> T {
+    let budget = Budget {
+        limits,
+        steps: 0,
+        depth: 0,
+        memory: 0,
+        deadline: limits.time.map(|time| Instant::now() + time),
+    };
+
+    let previous = BUDGET.with(|current| current.replace(Some(budget)));
+    let result = panic::catch_unwind(AssertUnwindSafe(run));
+    BUDGET.with(|current| *current.borrow_mut() = previous);
+
+    match result {
+        Ok(value) => value,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+/// Counts one step of execution, stopping the script if it has run out of
+/// steps or time
+pub(crate) fn step() {
+    BUDGET.with(|budget| {
+        let mut budget = budget.borrow_mut();
+        let budget = match budget.as_mut() {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        budget.steps += 1;
+
+        if let Some(steps) = budget.limits.steps {
+            if budget.steps > steps {
+                exceeded(Limit::Steps(steps));
+            }
+        }
+
+        if let (Some(deadline), Some(time)) = (budget.deadline, budget.limits.time) {
+            if budget.steps % TIME_CHECK_INTERVAL == 0 && Instant::now() > deadline {
+                exceeded(Limit::Time(time));
+            }
+        }
+    })
+}
+
+/// Counts memory that a script has allocated
+pub(crate) fn allocate(bytes: usize) {
+    BUDGET.with(|budget| {
+        let mut budget = budget.borrow_mut();
+        let budget = match budget.as_mut() {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        budget.memory += bytes;
+
+        if let Some(memory) = budget.limits.memory {
+            if budget.memory > memory {
+                exceeded(Limit::Memory(memory));
+            }
+        }
+    })
+}
+
+/// Counts the memory of a string that a rust function has created. Lists and
+/// maps are counted when they are created
+pub(crate) fn allocate_result(value: &Value) {
+    if let Value::String(string) = value {
+        allocate(string.len());
+    }
+}
+
+/// Stops the script if `depth` calls are nested, for engines that keep track of
+/// their own call stack
+pub(crate) fn check_depth(depth: usize) {
+    BUDGET.with(|budget| {
+        if let Some(Some(limit)) = budget
+            .borrow()
+            .as_ref()
+            .map(|budget| budget.limits.call_depth)
+        {
+            if depth > limit {
+                exceeded(Limit::CallDepth(limit));
+            }
+        }
+    })
+}
+
+/// Counts a function call until the returned guard is dropped, stopping the
+/// script if too many calls are nested
+pub(crate) fn enter_call() -> CallGuard {
+    BUDGET.with(|budget| {
+        if let Some(budget) = budget.borrow_mut().as_mut() {
+            budget.depth += 1;
+
+            if let Some(depth) = budget.limits.call_depth {
+                if budget.depth > depth {
+                    budget.depth -= 1;
+                    exceeded(Limit::CallDepth(depth));
+                }
+            }
+        }
+    });
+
+    CallGuard
+}
+
+pub(crate) struct CallGuard;
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        BUDGET.with(|budget| {
+            if let Some(budget) = budget.borrow_mut().as_mut() {
+                budget.depth -= 1;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::exceed_both;
+
+    #[test]
+    fn steps_are_reported_against_the_call_that_was_running() {
+        let limits = Limits {
+            steps: Some(200),
+            ..Limits::none()
+        };
+        let source = "let a = 1;\nmap(range(0, 1000), function (n) {\n    return n * n;\n});";
+
+        assert_eq!(exceed_both(source, limits), (Limit::Steps(200), Some(2)));
+    }
+
+    #[test]
+    fn memory_is_reported_against_the_call_that_was_running() {
+        let limits = Limits {
+            memory: Some(64),
+            ..Limits::none()
+        };
+        let source = "let s = \"data\";\nfunction grow(s) {\n    return grow(s + s);\n};\ngrow(s);";
+
+        assert_eq!(exceed_both(source, limits), (Limit::Memory(64), Some(3)));
+    }
+
+    #[test]
+    fn time_is_reported_against_the_call_that_was_running() {
+        let limits = Limits {
+            time: Some(Duration::ZERO),
+            ..Limits::none()
+        };
+        let source = "let a = 1;\nmap(range(0, 5000), function (n) {\n    return n;\n});";
+
+        assert_eq!(
+            exceed_both(source, limits),
+            (Limit::Time(Duration::ZERO), Some(2))
+        );
+    }
+
+    #[test]
+    fn call_depth_is_reported_against_the_call_that_went_too_deep() {
+        let limits = Limits {
+            call_depth: Some(10),
+            ..Limits::none()
+        };
+        let source = "function down(n) {\n    return down(n + 1);\n};\ndown(0);";
+
+        assert_eq!(exceed_both(source, limits), (Limit::CallDepth(10), Some(2)));
+    }
+
+    #[test]
+    fn limits_exceeded_outside_of_a_call_have_no_line() {
+        let limits = Limits {
+            steps: Some(5),
+            ..Limits::none()
+        };
+
+        assert_eq!(
+            exceed_both("let a = 1 + 2 + 3 + 4 + 5 + 6;", limits),
+            (Limit::Steps(5), None)
+        );
+    }
+}
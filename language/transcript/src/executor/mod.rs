@@ -0,0 +1,777 @@
+use core::panic;
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+use crate::{
+    file::{
+        FUNCTION_DEFINITION_CHARACTER, IMPORT_CHARACTER, NONE_CHARACTER, RETURN_CHARACTER,
+        VARIABLE_DEFINITION_CHARACTER,
+    },
+    translate::{
+        Binding, EqualitySymbol, FactorSymbol, TermSymbol, Token, TokenTypes, Variable, AST,
+    },
+    utils::warn_token,
+};
+
+pub use self::environment::Environment;
+pub(crate) use self::index::{get_index, set_index, slice};
+pub use self::limits::{enforce, Limit, LimitError, Limits, DEFAULT_CALL_DEPTH};
+pub use self::observer::{observe, Observer};
+
+mod environment;
+mod index;
+pub(crate) mod limits;
+mod observer;
+
+/// When less than this much stack is left, calls continue on a new stack
+/// segment rather than overflowing the stack of the thread running the script
+const STACK_RED_ZONE: usize = 1024 * 1024;
+const STACK_SEGMENT_SIZE: usize = 8 * 1024 * 1024;
+
+/// Rust functions are given a way to call other functions (e.g. the function
+/// passed to `map`), which runs them in whichever engine called the rust
+/// function. They are closures, so that they can hold on to state from the
+/// host (e.g. where `print` writes to).
+pub type RustFunctionBody = Rc<dyn Fn(Vec<Value>, &mut dyn Call) -> Value>;
+
+pub trait Call {
+    fn call(&mut self, function: &Value, args: Vec<Value>) -> Value;
+}
+
+/// The number of arguments that a rust function accepts
+#[derive(Clone, Copy, Debug)]
+pub enum Arity {
+    Exactly(usize),
+    Between(usize, usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exactly(expected) => count == expected,
+            Arity::Between(min, max) => (min..=max).contains(&count),
+            Arity::AtLeast(min) => count >= min,
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Exactly(expected) => write!(f, "{}", expected),
+            Arity::Between(min, max) => write!(f, "{} to {}", min, max),
+            Arity::AtLeast(min) => write!(f, "at least {}", min),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Value {
+    Number(f32),
+    String(String),
+    Boolean(bool),
+    Option(Option<Box<Value>>),
+    /// Lists are shared, so that changes made through one variable (or by
+    /// `push`) are visible through every other variable holding the list
+    List(Rc<RefCell<Vec<Value>>>),
+    /// Maps keep their keys in the order they were added. Like lists, they are
+    /// shared between every variable holding them
+    Map(Rc<RefCell<Vec<(String, Value)>>>),
+    Function {
+        /// The name the function was defined with, or `<anonymous>`
+        name: String,
+        args: Vec<String>,
+        body: Box<AST>,
+        closure: Rc<RefCell<Environment>>,
+    },
+    RustFunction {
+        name: String,
+        args: Vec<String>,
+        arity: Arity,
+        body: RustFunctionBody,
+    },
+    CompiledFunction(Rc<crate::vm::Closure>),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", format_number(*n)),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Option(None) => write!(f, "none"),
+            Value::Option(Some(v)) => write!(f, "some({})", v),
+            Value::List(l) => {
+                write!(f, "[")?;
+                for (i, v) in l.borrow().iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(m) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in m.borrow().iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "\"{}\": {}", k, v)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Function { args, .. } => write!(f, "fn({}) {{ ... }}", args.join(", ")),
+            Value::RustFunction { args, .. } => {
+                write!(f, "fn({}) {{ [Binary Code] }}", args.join(", "))
+            }
+            Value::CompiledFunction(closure) => {
+                write!(
+                    f,
+                    "fn({}) {{ [Bytecode] }}",
+                    closure.function.args.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Numbers are printed the same way on every machine, so that script output
+/// can be compared between runs. Whole numbers have no decimal point, negative
+/// zero is printed as zero and everything else is the shortest decimal that
+/// reads back as the same number.
+fn format_number(number: f32) -> String {
+    if number.is_nan() {
+        String::from("nan")
+    } else if number.is_infinite() {
+        String::from(if number > 0.0 { "inf" } else { "-inf" })
+    } else if number == 0.0 {
+        String::from("0")
+    } else {
+        number.to_string()
+    }
+}
+
+/// Values are compared structurally. Functions are never equal to anything,
+/// not even themselves, since there is no way to tell if two functions do the
+/// same thing.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Boolean(l), Value::Boolean(r)) => l == r,
+            (Value::Option(l), Value::Option(r)) => l == r,
+            (Value::List(l), Value::List(r)) => *l.borrow() == *r.borrow(),
+            // Maps with the same entries are equal, even if they were added in
+            // a different order
+            (Value::Map(l), Value::Map(r)) => {
+                let (l, r) = (l.borrow(), r.borrow());
+
+                l.len() == r.len() && l.iter().all(|entry| r.iter().any(|other| entry == other))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    pub fn list(items: Vec<Value>) -> Value {
+        limits::allocate(items.len() * std::mem::size_of::<Value>());
+
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    /// Creates a map, where later entries replace earlier ones with the same
+    /// key
+    pub fn map(entries: Vec<(String, Value)>) -> Value {
+        let mut unique: Vec<(String, Value)> = Vec::with_capacity(entries.len());
+
+        for (key, value) in entries {
+            match unique.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, existing)) => *existing = value,
+                None => unique.push((key, value)),
+            }
+        }
+
+        limits::allocate(
+            unique
+                .iter()
+                .map(|(key, _)| key.len() + std::mem::size_of::<(String, Value)>())
+                .sum(),
+        );
+
+        Value::Map(Rc::new(RefCell::new(unique)))
+    }
+
+    /// Creates a string from two others, e.g. for `+`
+    pub(crate) fn concatenate(left: String, right: &str) -> Value {
+        limits::allocate(right.len());
+
+        Value::String(left + right)
+    }
+}
+
+pub(crate) fn warn_about_text_tokens(token: &Token) {
+    // We want to provide a warning to the user if they are directly
+    // running a script to recommend that they pack it. Maybe in the
+    // future this will become a hard error.
+    warn_token(token, "Running scripts containing text-based keywords is not recommended. You should pack your scripts instead");
+}
+
+fn binding(variable: &Variable) -> Binding {
+    variable
+        .binding
+        .expect("Variables must be resolved before they are executed")
+}
+
+/// The messages for errors that both engines can raise, so that scripts fail
+/// the same way on either of them
+pub(crate) fn operator_error(operator: impl Display, left: &Value, right: &Value) -> String {
+    format!(
+        "Cannot use '{}' on {} and {}",
+        operator,
+        type_name(left),
+        type_name(right)
+    )
+}
+
+pub(crate) fn unary_error(operator: impl Display, value: &Value) -> String {
+    format!("Cannot use '{}' on {}", operator, type_name(value))
+}
+
+pub(crate) fn propagate_error(value: &Value) -> String {
+    format!(
+        "Cannot use '?' on {}, it is not an option",
+        type_name(value)
+    )
+}
+
+/// The resolver reports undefined variables before a script runs, so this is
+/// only for variables whose slot is still empty when it is used
+pub(crate) fn undefined(name: &str) -> String {
+    format!("Undefined variable: {}", name)
+}
+
+/// The type of a value, for error messages
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Boolean(_) => "a boolean",
+        Value::Option(_) => "an option",
+        Value::List(_) => "a list",
+        Value::Map(_) => "a map",
+        Value::Function { .. } | Value::RustFunction { .. } | Value::CompiledFunction(_) => {
+            "a function"
+        }
+    }
+}
+
+/// Functions capture the environment they are created in, so that they can
+/// keep using the variables around them after that scope has finished (e.g. a
+/// counter returned from a function)
+fn create_function(
+    name: &str,
+    params: &[Token],
+    body: &AST,
+    env: &Rc<RefCell<Environment>>,
+) -> Value {
+    Value::Function {
+        name: name.to_string(),
+        args: params
+            .iter()
+            .map(|token| match &token.token_type {
+                TokenTypes::Identifier { value } => value.to_string(),
+                _ => panic!("Function parameters must be identifiers!"),
+            })
+            .collect(),
+        body: Box::new(body.clone()),
+        closure: env.clone(),
+    }
+}
+
+/// An error found while running a script. Runtime errors unwind out of the
+/// engine carrying this, so that whoever is running the script can report it
+/// against the token that caused it. They unwind with `resume_unwind`, which
+/// skips the panic hook, since they are caught and are not crashes.
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub token: Token,
+    pub message: String,
+}
+
+pub fn runtime_error(token: &Token, message: &str) -> ! {
+    std::panic::resume_unwind(Box::new(RuntimeError {
+        token: token.clone(),
+        message: message.to_string(),
+    }))
+}
+
+thread_local! {
+    /// The innermost call in the script that is running, which errors raised
+    /// by rust functions (e.g. a builtin given the wrong type of value) and
+    /// exceeded limits are reported against
+    static CALLER: RefCell<Option<Token>> = const { RefCell::new(None) };
+}
+
+/// Marks a call as running until it is dropped, then goes back to the call
+/// that was running before it
+pub(crate) struct Caller(Option<Token>);
+
+impl Drop for Caller {
+    fn drop(&mut self) {
+        CALLER.with(|caller| *caller.borrow_mut() = self.0.take());
+    }
+}
+
+pub(crate) fn enter_caller(token: &Token) -> Caller {
+    Caller(CALLER.with(|caller| caller.replace(Some(token.clone()))))
+}
+
+/// Keeps the call that is running now, so that it is running again once the
+/// guard is dropped, whatever calls were entered in between
+pub(crate) fn save_caller() -> Caller {
+    Caller(caller())
+}
+
+pub(crate) fn caller() -> Option<Token> {
+    CALLER.with(|caller| caller.borrow().clone())
+}
+
+/// Reports an error against the call to the rust function that is running.
+/// Builtins use this for arguments that they cannot use, e.g. `len(1)`. When a
+/// host calls a function directly there is no call in the script to report
+/// the error against, so only the message is given.
+pub fn call_error(message: &str) -> ! {
+    match caller() {
+        Some(token) => runtime_error(&token, message),
+        None => std::panic::resume_unwind(Box::new(message.to_string())),
+    }
+}
+
+pub(crate) fn arity_error(name: &str, expected: impl Display, got: usize) -> String {
+    let expected = expected.to_string();
+    let arguments = match expected.as_str() {
+        "1" => "argument",
+        _ => "arguments",
+    };
+
+    format!("{} expects {} {}, got {}", name, expected, arguments, got)
+}
+
+/// Anything that stops statements from running in order. These are passed up
+/// through `execute` with `?` until something handles them, so they unwind
+/// through every block in between.
+pub enum Unwind {
+    Return(Value),
+}
+
+pub type ExecuteResult = Result<Value, Unwind>;
+
+struct TreeWalker;
+
+impl Call for TreeWalker {
+    fn call(&mut self, function: &Value, args: Vec<Value>) -> Value {
+        call_function(function, args)
+    }
+}
+
+/// Calls a function from a call in the script, reporting errors against the
+/// token of the call
+fn call_at(function: &Value, args: Vec<Value>, token: &Token) -> Value {
+    match function {
+        Value::Function {
+            name, args: params, ..
+        } if params.len() != args.len() => {
+            runtime_error(token, &arity_error(name, params.len(), args.len()))
+        }
+        Value::Function { .. } | Value::RustFunction { .. } => {
+            let _caller = enter_caller(token);
+            call_function(function, args)
+        }
+        value => runtime_error(
+            token,
+            &format!("Cannot call {}, it is not a function", type_name(value)),
+        ),
+    }
+}
+
+/// Calls a function with arguments that have already been evaluated
+pub fn call_function(function: &Value, args: Vec<Value>) -> Value {
+    match function {
+        Value::Function {
+            name,
+            args: params,
+            body,
+            closure,
+        } => {
+            if args.len() != params.len() {
+                call_error(&arity_error(name, params.len(), args.len()));
+            }
+
+            let _call = limits::enter_call();
+            let _function = observer::enter(name);
+            let mut enclosing_environment = Environment::from_enclosing(closure.clone());
+
+            for (slot, arg) in args.into_iter().enumerate() {
+                enclosing_environment.define_slot(slot, &params[slot], arg);
+            }
+
+            let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+                execute(body, enclosing_environment.contain())
+            });
+
+            // Functions without a return statement return none
+            match result {
+                Ok(_) => Value::Option(None),
+                Err(Unwind::Return(value)) => value,
+            }
+        }
+        Value::RustFunction {
+            name, arity, body, ..
+        } => {
+            if !arity.accepts(args.len()) {
+                call_error(&arity_error(name, arity, args.len()));
+            }
+
+            let _function = observer::enter(name);
+            let result = body(args, &mut TreeWalker);
+            limits::allocate_result(&result);
+
+            result
+        }
+        value => call_error(&format!(
+            "Cannot call {}, it is not a function",
+            type_name(value)
+        )),
+    }
+}
+
+/// Executes an AST that has already been through `translate::resolve`
+pub fn execute(code: &AST, env: Rc<RefCell<Environment>>) -> ExecuteResult {
+    limits::step();
+
+    Ok(match code {
+        AST::Block { statements } => {
+            for statement in statements {
+                observer::statement(statement, &env);
+                execute(statement, env.clone())?;
+            }
+
+            Value::Option(None)
+        }
+        AST::VariableDefinition {
+            variable,
+            value,
+            keyword_token,
+        } => {
+            if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                if value != VARIABLE_DEFINITION_CHARACTER {
+                    warn_about_text_tokens(keyword_token);
+                }
+            }
+
+            let value = execute(value, env.clone())?;
+            env.borrow_mut()
+                .define_slot(binding(variable).slot, &variable.name, value);
+
+            Value::Option(None)
+        }
+        AST::FunctionDefinition {
+            variable,
+            params,
+            body,
+            keyword_token,
+        } => {
+            if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                if value != FUNCTION_DEFINITION_CHARACTER {
+                    warn_about_text_tokens(keyword_token);
+                }
+            }
+
+            let function = create_function(&variable.name, params, body, &env);
+
+            env.borrow_mut()
+                .define_slot(binding(variable).slot, &variable.name, function);
+
+            Value::Option(None)
+        }
+        AST::Function {
+            params,
+            body,
+            keyword_token,
+        } => {
+            if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                if value != FUNCTION_DEFINITION_CHARACTER {
+                    warn_about_text_tokens(keyword_token);
+                }
+            }
+
+            create_function("<anonymous>", params, body, &env)
+        }
+        AST::FunctionCall {
+            function,
+            args,
+            token,
+        } => {
+            let function = execute(function, env.clone())?;
+            let args = args
+                .iter()
+                .map(|arg| execute(arg, env.clone()))
+                .collect::<Result<Vec<Value>, Unwind>>()?;
+
+            call_at(&function, args, token)
+        }
+        AST::IndexAssignment { target, value } => {
+            let (collection, index, token) = match target.as_ref() {
+                AST::Index {
+                    value,
+                    index,
+                    token,
+                } => (
+                    execute(value, env.clone())?,
+                    execute(index, env.clone())?,
+                    token,
+                ),
+                AST::Field { value, name, token } => (
+                    execute(value, env.clone())?,
+                    Value::String(name.to_string()),
+                    token,
+                ),
+                _ => unreachable!("The parser only assigns to indexes and fields"),
+            };
+            let value = execute(value, env)?;
+
+            set_index(&collection, &index, value, token);
+
+            Value::Option(None)
+        }
+        AST::Assignment { variable, value } => {
+            let value = execute(value, env.clone())?;
+            let Binding { depth, slot } = binding(variable);
+
+            if !env.borrow_mut().assign_slot(depth, slot, value) {
+                runtime_error(&variable.token, &undefined(&variable.name));
+            }
+
+            Value::Option(None)
+        }
+        AST::Return {
+            value,
+            keyword_token,
+        } => {
+            if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                if value != RETURN_CHARACTER {
+                    warn_about_text_tokens(keyword_token);
+                }
+            }
+
+            let value = match value {
+                Some(value) => execute(value, env)?,
+                None => Value::Option(None),
+            };
+
+            return Err(Unwind::Return(value));
+        }
+        AST::Propagate { value, token } => match execute(value, env)? {
+            Value::Option(Some(value)) => *value,
+            Value::Option(None) => return Err(Unwind::Return(Value::Option(None))),
+            value => runtime_error(token, &propagate_error(&value)),
+        },
+        AST::Equality(left, op, right) => {
+            let left = execute(left, env.clone())?;
+            let right = execute(right, env.clone())?;
+
+            Value::Boolean(match op {
+                EqualitySymbol::Equal => left == right,
+                EqualitySymbol::NotEqual => left != right,
+            })
+        }
+        AST::Term(left, op, right, token) => {
+            let left = execute(left, env.clone())?;
+            let right = execute(right, env.clone())?;
+
+            match (op, left, right) {
+                (TermSymbol::Add, Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+                (TermSymbol::Add, Value::String(l), Value::String(r)) => Value::concatenate(l, &r),
+                (TermSymbol::Sub, Value::Number(l), Value::Number(r)) => Value::Number(l - r),
+                (op, left, right) => runtime_error(token, &operator_error(op, &left, &right)),
+            }
+        }
+        AST::Factor(left, op, right, token) => {
+            let left = execute(left, env.clone())?;
+            let right = execute(right, env.clone())?;
+
+            match (left, right) {
+                (Value::Number(l), Value::Number(r)) => Value::Number(match op {
+                    FactorSymbol::Mul => l * r,
+                    FactorSymbol::Div => l / r,
+                }),
+                (left, right) => runtime_error(token, &operator_error(op, &left, &right)),
+            }
+        }
+        AST::Unary(term, ast, token) => {
+            let value = execute(ast, env.clone())?;
+
+            Value::Number(match value {
+                Value::Number(n) => match term {
+                    TermSymbol::Add => n,
+                    TermSymbol::Sub => -n,
+                },
+                value => runtime_error(token, &unary_error(term, &value)),
+            })
+        }
+        AST::Group(expression) => execute(expression, env)?,
+        AST::List { items } => Value::list(
+            items
+                .iter()
+                .map(|item| execute(item, env.clone()))
+                .collect::<Result<Vec<Value>, Unwind>>()?,
+        ),
+        AST::Index {
+            value,
+            index,
+            token,
+        } => {
+            let value = execute(value, env.clone())?;
+            let index = execute(index, env)?;
+
+            get_index(&value, &index, token)
+        }
+        AST::Map { entries } => Value::map(
+            entries
+                .iter()
+                .map(|(key, value)| Ok((key.to_string(), execute(value, env.clone())?)))
+                .collect::<Result<Vec<(String, Value)>, Unwind>>()?,
+        ),
+        AST::Field { value, name, token } => {
+            let value = execute(value, env)?;
+
+            get_index(&value, &Value::String(name.to_string()), token)
+        }
+        AST::Slice {
+            value,
+            start,
+            end,
+            token,
+        } => {
+            let value = execute(value, env.clone())?;
+            let bound = |bound: &Option<Box<AST>>| match bound {
+                Some(bound) => execute(bound, env.clone()),
+                None => Ok(Value::Option(None)),
+            };
+            let start = bound(start)?;
+            let end = bound(end)?;
+
+            slice(&value, &start, &end, token)
+        }
+        AST::Number(number) => Value::Number(*number),
+        AST::String(string) => Value::String(string.to_string()),
+        AST::Identifier(variable) => {
+            let Binding { depth, slot } = binding(variable);
+
+            env.borrow()
+                .get_slot(depth, slot)
+                .unwrap_or_else(|| runtime_error(&variable.token, &undefined(&variable.name)))
+        }
+        AST::Import {
+            variable,
+            keyword_token,
+            ..
+        } => {
+            if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                if value != IMPORT_CHARACTER {
+                    warn_about_text_tokens(keyword_token);
+                }
+            }
+
+            let Binding { depth, slot } = binding(variable);
+
+            env.borrow()
+                .get_slot(depth, slot)
+                .expect("Modules are loaded before the script runs")
+        }
+        AST::None { keyword_token } => {
+            if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                if value != NONE_CHARACTER {
+                    warn_about_text_tokens(keyword_token);
+                }
+            }
+
+            Value::Option(None)
+        }
+        AST::Comment { value: _ } => Value::Option(None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::fail_both;
+
+    fn error(message: &str, line: usize) -> (String, Option<usize>) {
+        (message.to_string(), Some(line))
+    }
+
+    #[test]
+    fn operators_report_the_types_they_were_given() {
+        assert_eq!(
+            fail_both("let x = 1;\nx = x + \"a\";"),
+            error("Cannot use '+' on a number and a string", 2)
+        );
+        assert_eq!(
+            fail_both("let x = [1] *\n2;"),
+            error("Cannot use '*' on a list and a number", 1)
+        );
+        assert_eq!(
+            fail_both("let x = -\"a\";"),
+            error("Cannot use '-' on a string", 1)
+        );
+    }
+
+    #[test]
+    fn calls_report_the_wrong_number_of_arguments() {
+        assert_eq!(
+            fail_both("function add(a, b) { return a + b; };\n\nadd(1);"),
+            error("add expects 2 arguments, got 1", 3)
+        );
+        assert_eq!(
+            fail_both("len(\"a\", \"b\");"),
+            error("len expects 1 argument, got 2", 1)
+        );
+    }
+
+    #[test]
+    fn only_functions_can_be_called() {
+        assert_eq!(
+            fail_both("let x = 1;\nx();"),
+            error("Cannot call a number, it is not a function", 2)
+        );
+    }
+
+    #[test]
+    fn builtins_report_errors_against_their_call() {
+        assert_eq!(
+            fail_both("let x = 1;\nprint(upper(x));"),
+            error("upper expects a string, got a number", 2)
+        );
+    }
+
+    #[test]
+    fn errors_in_callbacks_are_reported_in_the_callback() {
+        let source = "map([1], function (x) {\n    return x + \"a\";\n});";
+
+        assert_eq!(
+            fail_both(source),
+            error("Cannot use '+' on a number and a string", 2)
+        );
+    }
+
+    #[test]
+    fn only_options_can_be_propagated() {
+        assert_eq!(
+            fail_both("function first() {\n    return 1?;\n};\nfirst();"),
+            error("Cannot use '?' on a number, it is not an option", 2)
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::translate::AST;
+
+use super::Environment;
+
+/// Something that watches the tree walker run a script, e.g. a debugger or a
+/// profiler. Every method does nothing by default, so observers only implement
+/// the events they care about.
+pub trait Observer {
+    /// Called before each statement of a block is executed, with the
+    /// environment that it runs in
+    fn statement(&mut self, _statement: &AST, _env: &Rc<RefCell<Environment>>) {}
+
+    /// Called when a function starts running. Rust functions are included, so
+    /// that time spent in builtins can be told apart from the script
+    fn enter(&mut self, _function: &str) {}
+
+    /// Called when the function that was entered last has finished, including
+    /// when it is unwinding because of an error
+    fn exit(&mut self) {}
+}
+
+thread_local! {
+    static OBSERVER: RefCell<Option<Rc<RefCell<dyn Observer>>>> = const { RefCell::new(None) };
+}
+
+/// Runs `run`, telling `observer` about everything the tree walker does. The
+/// caller keeps its own handle on the observer, to look at what it recorded
+/// afterwards.
+pub fn observe<T>(observer: Rc<RefCell<dyn Observer>>, run: impl FnOnce() -> T) -> T {
+    let previous = OBSERVER.with(|current| current.replace(Some(observer)));
+    let _restore = Restore(previous);
+
+    run()
+}
+
+/// Puts back the observer that was there before `observe`, even if the script
+/// unwinds
+struct Restore(Option<Rc<RefCell<dyn Observer>>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        OBSERVER.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+fn notify(event: impl FnOnce(&mut dyn Observer)) {
+    let observer = OBSERVER.with(|current| current.borrow().clone());
+
+    if let Some(observer) = observer {
+        if let Ok(mut observer) = observer.try_borrow_mut() {
+            event(&mut *observer);
+        }
+    }
+}
+
+pub(crate) fn statement(statement: &AST, env: &Rc<RefCell<Environment>>) {
+    notify(|observer| observer.statement(statement, env));
+}
+
+/// Tells the observer about a function call until the returned guard is
+/// dropped
+pub(crate) fn enter(function: &str) -> FunctionGuard {
+    notify(|observer| observer.enter(function));
+
+    FunctionGuard
+}
+
+pub(crate) struct FunctionGuard;
+
+impl Drop for FunctionGuard {
+    fn drop(&mut self) {
+        notify(|observer| observer.exit());
+    }
+}
@@ -0,0 +1,45 @@
+pub const VARIABLE_DEFINITION_CHARACTER: &str = ""; // E000
+pub const FUNCTION_DEFINITION_CHARACTER: &str = ""; // E001
+pub const RETURN_CHARACTER: &str = ""; // E002
+pub const NONE_CHARACTER: &str = ""; // E003
+pub const IMPORT_CHARACTER: &str = ""; // E004
+
+/// The packed characters of the builtin functions. Packed files refer to
+/// builtins by these characters, so they are unpacked in the language of the
+/// reader. Characters must never be reused, new builtins go on the end.
+pub const BUILTIN_CHARACTERS: &[(&str, &str)] = &[
+    ("print", ""),      // E100
+    ("input", ""),      // E101
+    ("some", ""),       // E102
+    ("is_none", ""),    // E103
+    ("unwrap_or", ""),  // E104
+    ("push", ""),       // E105
+    ("pop", ""),        // E106
+    ("map", ""),        // E107
+    ("filter", ""),     // E108
+    ("range", ""),      // E109
+    ("keys", ""),       // E10A
+    ("values", ""),     // E10B
+    ("has", ""),        // E10C
+    ("len", ""),        // E10D
+    ("upper", ""),      // E10E
+    ("lower", ""),      // E10F
+    ("trim", ""),       // E110
+    ("split", ""),      // E111
+    ("join", ""),       // E112
+    ("replace", ""),    // E113
+    ("format", ""),     // E114
+    ("floor", ""),      // E115
+    ("sqrt", ""),       // E116
+    ("pow", ""),        // E117
+    ("min", ""),        // E118
+    ("max", ""),        // E119
+    ("random", ""),     // E11A
+    ("to_number", ""),  // E11B
+    ("to_string", ""),  // E11C
+    ("type_of", ""),    // E11D
+    ("read_file", ""),  // E11E
+    ("write_file", ""), // E11F
+    ("get_env", ""),    // E120
+    ("clock", ""),      // E121
+];
@@ -0,0 +1,112 @@
+use crate::{
+    translate::{self, Token, TokenTypes},
+    utils::Config,
+    Error,
+};
+
+use super::{glossary, is_packed, pack, unpack, Glossary, BUILTIN_CHARACTERS};
+
+/// A script that has been translated into another locale
+pub struct Conversion {
+    pub source: String,
+    /// Names defined by the script that are keywords in the new locale, or that
+    /// a builtin is written as in the new locale but not in the old one, at
+    /// each place they are used in the original script. These would be read as
+    /// the keyword, or would newly shadow the builtin, after translating.
+    pub collisions: Vec<Token>,
+}
+
+/// Translates a script written for `from` into the locale of `to`. This packs
+/// the script and unpacks it again, so keywords, builtins and the names in the
+/// glossary are all translated, and the script is laid out the way `pack`
+/// lays it out.
+pub fn convert(mut tokens: Vec<Token>, from: &Config, to: &Config) -> Result<Conversion, Error> {
+    if let Some(glossary) = Glossary::load(from)? {
+        glossary.normalize(&mut tokens, from)?;
+    }
+
+    let names = tokens.clone();
+    let packed = pack(&translate::parse(&mut tokens, from), from);
+
+    // Keywords and the builtins that the script uses are packed into their
+    // characters, so every name that is still written out is one that the
+    // script defined. A definition may shadow a builtin, which only matters if
+    // translating gives that builtin the definition's name
+    let packed_tokens = translate::Tokenizer::from_string(packed.to_string()).tokenize();
+    let reserved: Vec<&str> = glossary::identifiers(&packed_tokens)
+        .filter_map(|token| match &token.token_type {
+            TokenTypes::Identifier { value }
+                if !is_packed(value)
+                    && (glossary::is_keyword(value, to) || is_builtin_name(value, from, to)) =>
+            {
+                Some(value.as_str())
+            }
+            _ => None,
+        })
+        .collect();
+
+    let collisions = glossary::identifiers(&names)
+        .filter(|token| {
+            matches!(&token.token_type, TokenTypes::Identifier { value } if reserved.contains(&value.as_str()))
+        })
+        .cloned()
+        .collect();
+
+    Ok(Conversion {
+        source: unpack(packed, to)?,
+        collisions,
+    })
+}
+
+/// Whether unpacking for `to` writes a builtin as `name`, when `from` writes
+/// it with a different name. A name that both locales give the builtin already
+/// shadowed it before translating.
+fn is_builtin_name(name: &str, from: &Config, to: &Config) -> bool {
+    BUILTIN_CHARACTERS
+        .iter()
+        .any(|(english, _)| to.builtin_name(english) == name && from.builtin_name(english) != name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate::Tokenizer;
+
+    fn collisions(source: &str, to: &Config) -> usize {
+        let tokens = Tokenizer::from_string(source.to_string()).tokenize();
+
+        convert(tokens, &Config::default(), to)
+            .unwrap()
+            .collisions
+            .len()
+    }
+
+    fn finnish() -> Config {
+        let mut config = Config::default();
+        config
+            .builtin_names
+            .push((String::from("values"), String::from("arvot")));
+        config
+    }
+
+    #[test]
+    fn shadowing_a_builtin_that_is_translated_is_not_a_collision() {
+        let source = "function total(values) {\n    return len(values);\n};";
+
+        assert_eq!(collisions(source, &finnish()), 0);
+    }
+
+    #[test]
+    fn shadowing_a_builtin_that_is_not_translated_is_not_a_collision() {
+        let source = "function count(values) {\n    return len(values);\n};";
+
+        assert_eq!(collisions(source, &Config::default()), 0);
+    }
+
+    #[test]
+    fn names_that_builtins_are_translated_to_collide() {
+        let source = "let arvot = 1;\nprint(values({\"a\": arvot}));";
+
+        assert_eq!(collisions(source, &finnish()), 2);
+    }
+}
@@ -0,0 +1,347 @@
+use tini::Ini;
+
+use crate::{
+    translate::{self, Binding, ResolveError, Token, TokenTypes, Tokenizer},
+    utils::Config,
+    Error,
+};
+
+use super::pack::builtins;
+
+/// Translations of the names that a project defines, read from the glossary
+/// file configured in the `[glossary]` section of transcript.ini. Packed files
+/// always use the canonical names, which are translated when unpacking.
+///
+/// ```ini
+/// [fi]
+/// make_counter = tee_laskuri
+/// ```
+pub struct Glossary {
+    /// Pairs of canonical names and their translation in the configured locale
+    names: Vec<(String, String)>,
+}
+
+impl Glossary {
+    /// Loads the glossary for the configured locale, if there is one. Fails if
+    /// the glossary translates two names to the same thing or translates a
+    /// name to a keyword or builtin.
+    pub fn load(config: &Config) -> Result<Option<Glossary>, Error> {
+        let (path, locale) = match (&config.glossary, &config.locale) {
+            (Some(path), Some(locale)) => (path, locale),
+            _ => return Ok(None),
+        };
+
+        let names: Vec<(String, String)> = Ini::from_file(path)
+            .map_err(|error| Error::Config(format!("{}: {}", path, error)))?
+            .section_iter(locale)
+            .map(|(canonical, translated)| (canonical.to_string(), translated.to_string()))
+            .collect();
+
+        for (index, (canonical, translated)) in names.iter().enumerate() {
+            if is_reserved(translated, config) {
+                return Err(Error::Config(format!(
+                    "{}: '{}' cannot be translated to '{}', because it is a keyword or builtin",
+                    path, canonical, translated
+                )));
+            }
+
+            if let Some((other, _)) = names[..index].iter().find(|(_, other)| other == translated) {
+                return Err(Error::Config(format!(
+                    "{}: '{}' and '{}' are both translated to '{}'",
+                    path, other, canonical, translated
+                )));
+            }
+        }
+
+        Ok(Some(Glossary { names }))
+    }
+
+    /// Renames translated identifiers back to their canonical names, before a
+    /// script is packed. Fails if a renamed name would refer to a different
+    /// variable than before, e.g. because a variable with the canonical name is
+    /// visible where the translated one is used.
+    pub fn normalize(&self, tokens: &mut [Token], config: &Config) -> Result<(), Error> {
+        let mut normalized = tokens.to_vec();
+        rename(&mut normalized, |name| {
+            self.names
+                .iter()
+                .find(|(_, translated)| translated == name)
+                .map(|(canonical, _)| canonical)
+        });
+
+        if let Some(token) = conflict(tokens, &normalized, config) {
+            let (canonical, translated) = self.pair(&token, |(canonical, _)| canonical);
+
+            return Err(Error::Script {
+                message: format!(
+                    "'{}' is used alongside its translation '{}', so they cannot be told apart",
+                    canonical, translated
+                ),
+                token,
+            });
+        }
+
+        tokens.clone_from_slice(&normalized);
+
+        Ok(())
+    }
+
+    /// Checks that the names of a whole packed script can be translated, i.e.
+    /// that no translation is the name of another variable that is visible
+    /// where the translated name is used
+    pub fn check_translation(&self, source: &str, config: &Config) -> Result<(), Error> {
+        let tokens = Tokenizer::from_string(source.to_string()).tokenize();
+        let mut translated = tokens.clone();
+        rename(&mut translated, |name| {
+            self.names
+                .iter()
+                .find(|(canonical, _)| canonical == name)
+                .map(|(_, translated)| translated)
+        });
+
+        match conflict(&tokens, &translated, config) {
+            Some(token) => {
+                let (canonical, translated) = self.pair(&token, |(_, translated)| translated);
+
+                Err(Error::Script {
+                    message: format!(
+                        "'{}' is already used, so '{}' cannot be translated to it",
+                        translated, canonical
+                    ),
+                    token,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The names that `token` was renamed with, found by the name it has
+    /// after renaming
+    fn pair(
+        &self,
+        token: &Token,
+        renamed: impl Fn(&(String, String)) -> &String,
+    ) -> &(String, String) {
+        self.names
+            .iter()
+            .find(|pair| is_named(token, renamed(pair)))
+            .expect("Only renamed names can conflict!")
+    }
+
+    /// Renames canonical identifiers in a packed script (or a piece of one) to
+    /// their translations. Whole scripts are checked with `check_translation`
+    /// first.
+    pub fn translate(&self, source: String) -> String {
+        let tokens = Tokenizer::from_string(source.clone()).tokenize();
+
+        let mut lines: Vec<Vec<char>> = source
+            .split('\n')
+            .map(|line| line.chars().collect())
+            .collect();
+
+        // Going backwards keeps the columns of earlier tokens on a line correct
+        for token in identifiers(&tokens)
+            .collect::<Vec<&Token>>()
+            .into_iter()
+            .rev()
+        {
+            let translated = self
+                .names
+                .iter()
+                .find(|(canonical, _)| is_named(token, canonical));
+
+            if let Some((_, translated)) = translated {
+                lines[token.line - 1].splice(token.start..token.end, translated.chars());
+            }
+        }
+
+        lines
+            .into_iter()
+            .map(|line| line.into_iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Renames every identifier that `rename` gives a new name for
+fn rename<'a>(tokens: &mut [Token], rename: impl Fn(&str) -> Option<&'a String>) {
+    let fields = fields(tokens);
+
+    for (index, token) in tokens.iter_mut().enumerate() {
+        if fields.contains(&index) {
+            continue;
+        }
+
+        if let TokenTypes::Identifier { value } = &mut token.token_type {
+            if let Some(name) = rename(value) {
+                *value = name.to_string();
+            }
+        }
+    }
+}
+
+/// Finds the first name in `renamed` that refers to something else than it
+/// did in `tokens`, which happens when a renamed name meets another variable
+/// of the same name in a scope where both are visible. Names in scopes that
+/// cannot see each other are free to be the same.
+fn conflict(tokens: &[Token], renamed: &[Token], config: &Config) -> Option<Token> {
+    let (bindings, errors) = resolution(tokens, config);
+    let (renamed_bindings, renamed_errors) = resolution(renamed, config);
+
+    // Two definitions in one scope are checked first, since they also move the
+    // slots of the definitions after them
+    let same_place = |a: &Token, b: &Token| a.line == b.line && a.start == b.start;
+    if let Some(error) = renamed_errors.into_iter().find(|error| {
+        !errors
+            .iter()
+            .any(|before| same_place(&before.token, &error.token))
+    }) {
+        return Some(error.token);
+    }
+
+    bindings
+        .into_iter()
+        .zip(renamed_bindings)
+        .find(|((_, before), (_, after))| before != after)
+        .map(|(_, (token, _))| token)
+}
+
+/// Parses and resolves a script, returning what every name in it refers to
+/// and the problems that the resolver found
+fn resolution(
+    tokens: &[Token],
+    config: &Config,
+) -> (Vec<(Token, Option<Binding>)>, Vec<ResolveError>) {
+    let mut tokens = tokens.to_vec();
+    let mut ast = translate::parse(&mut tokens, config);
+    let errors = translate::resolve(&mut ast, &[builtins(config), Vec::new()])
+        .err()
+        .unwrap_or_default();
+
+    let bindings = ast
+        .variables()
+        .into_iter()
+        .map(|variable| (variable.token.clone(), variable.binding))
+        .collect();
+
+    (bindings, errors)
+}
+
+/// Whether `name` is read as a keyword or builtin rather than as a name
+pub(super) fn is_reserved(name: &str, config: &Config) -> bool {
+    is_keyword(name, config) || config.match_builtin(name).is_some()
+}
+
+/// Whether `name` is read as a keyword rather than as a name
+pub(super) fn is_keyword(name: &str, config: &Config) -> bool {
+    config.match_function_keyword(name)
+        || config.match_variable_keyword(name)
+        || config.match_return_keyword(name)
+        || config.match_none_keyword(name)
+        || config.match_import_keyword(name)
+}
+
+fn is_named(token: &Token, name: &str) -> bool {
+    matches!(&token.token_type, TokenTypes::Identifier { value } if value == name)
+}
+
+/// The indexes of identifiers that are field names (e.g. `key` in `map.key`).
+/// These are keys of a map rather than names, so they are never translated
+fn fields(tokens: &[Token]) -> Vec<usize> {
+    (1..tokens.len())
+        .filter(|index| tokens[index - 1].token_type == TokenTypes::Dot)
+        .collect()
+}
+
+/// Every identifier in a script that refers to a variable
+pub(super) fn identifiers(tokens: &[Token]) -> impl Iterator<Item = &Token> {
+    let fields = fields(tokens);
+
+    tokens
+        .iter()
+        .enumerate()
+        .filter(move |(index, token)| token.token_type.is_identifier() && !fields.contains(index))
+        .map(|(_, token)| token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glossary() -> Glossary {
+        Glossary {
+            names: vec![(String::from("counter"), String::from("laskuri"))],
+        }
+    }
+
+    fn normalized(source: &str) -> Result<Vec<String>, String> {
+        let mut tokens = Tokenizer::from_string(source.to_string()).tokenize();
+
+        match glossary().normalize(&mut tokens, &Config::default()) {
+            Ok(()) => Ok(identifiers(&tokens)
+                .filter_map(|token| match &token.token_type {
+                    TokenTypes::Identifier { value } => Some(value.to_string()),
+                    _ => None,
+                })
+                .collect()),
+            Err(Error::Script { message, .. }) => Err(message),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    fn checked(source: &str) -> Result<(), String> {
+        match glossary().check_translation(source, &Config::default()) {
+            Ok(()) => Ok(()),
+            Err(Error::Script { message, .. }) => Err(message),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn translations_are_normalized() {
+        assert_eq!(
+            normalized("let laskuri = 1;\nprint(laskuri);"),
+            Ok(vec![
+                String::from("let"),
+                String::from("counter"),
+                String::from("print"),
+                String::from("counter")
+            ])
+        );
+    }
+
+    #[test]
+    fn names_in_scopes_that_cannot_see_each_other_do_not_conflict() {
+        let source = "function a(counter) {\n    return counter;\n};\nfunction b(laskuri) {\n    return laskuri;\n};";
+
+        assert!(normalized(source).is_ok());
+        assert_eq!(checked(source), Ok(()));
+    }
+
+    #[test]
+    fn translations_that_shadow_their_canonical_name_conflict() {
+        let message =
+            "'counter' is used alongside its translation 'laskuri', so they cannot be told apart";
+
+        assert_eq!(
+            normalized(
+                "let counter = 1;\nfunction f(laskuri) {\n    return laskuri + counter;\n};"
+            ),
+            Err(String::from(message))
+        );
+        assert_eq!(
+            normalized("let counter = 1;\nlet laskuri = 2;"),
+            Err(String::from(message))
+        );
+    }
+
+    #[test]
+    fn translations_that_are_already_visible_conflict() {
+        assert_eq!(
+            checked("let counter = 1;\nfunction f(laskuri) {\n    return counter;\n};"),
+            Err(String::from(
+                "'laskuri' is already used, so 'counter' cannot be translated to it"
+            ))
+        );
+    }
+}
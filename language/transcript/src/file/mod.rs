@@ -0,0 +1,13 @@
+mod constants;
+mod convert;
+mod glossary;
+mod pack;
+mod source_map;
+mod unpack;
+
+pub use constants::*;
+pub use convert::*;
+pub use glossary::*;
+pub use pack::*;
+pub use source_map::*;
+pub use unpack::*;
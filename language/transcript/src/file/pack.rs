@@ -0,0 +1,272 @@
+use crate::{
+    translate::{self, Token, TokenTypes, Variable, AST},
+    utils::Config,
+};
+
+use super::{
+    BUILTIN_CHARACTERS, FUNCTION_DEFINITION_CHARACTER, IMPORT_CHARACTER, NONE_CHARACTER,
+    RETURN_CHARACTER, VARIABLE_DEFINITION_CHARACTER,
+};
+
+/// Builtins are packed into their characters wherever their name refers to the
+/// builtin. Variables that a script defines with the name of a builtin (e.g. a
+/// parameter called `values`) shadow it, so they and their uses keep the name.
+pub fn pack(ast: &AST, config: &Config) -> String {
+    let mut ast = ast.clone();
+
+    // Only the bindings are needed. Names that are not defined anywhere, e.g.
+    // the globals left by an earlier script, are not builtins, so they are
+    // left as they are
+    let _ = translate::resolve(&mut ast, &[builtins(config), Vec::new()]);
+
+    pack_node(&ast, config, 0)
+}
+
+/// Every name that a builtin can be called by
+pub(super) fn builtins(config: &Config) -> Vec<String> {
+    BUILTIN_CHARACTERS
+        .iter()
+        .flat_map(|(english, character)| [english, config.builtin_name(english), character])
+        .map(str::to_string)
+        .collect()
+}
+
+/// Packs a node that is inside of `functions` functions. Builtins are in the
+/// scope around the script, so a name refers to one when the resolver bound it
+/// one scope further out than the script.
+fn pack_node(ast: &AST, config: &Config, functions: usize) -> String {
+    // Every recursive call uses the same config
+    let pack = |ast: &AST| pack_node(ast, config, functions);
+    let name = |variable: &Variable| match variable.binding {
+        Some(binding) if binding.depth == functions + 1 => config
+            .match_builtin(&variable.name)
+            .unwrap_or(&variable.name)
+            .to_string(),
+        _ => variable.name.to_string(),
+    };
+
+    let mut output = String::new();
+
+    match ast {
+        AST::Block { statements } => {
+            for (index, statement) in statements.iter().enumerate() {
+                output.push_str(&pack(statement));
+                output.push(';');
+
+                if index != statements.len() - 1 {
+                    // We don't want to add a newline to the inside of blocks,
+                    // this causes weird formatting in stuff like functions.
+                    output.push('\n');
+                }
+            }
+        }
+        AST::VariableDefinition {
+            variable,
+            value,
+            keyword_token: _,
+        } => {
+            output.push_str(&format!(
+                "{} {} = {}",
+                VARIABLE_DEFINITION_CHARACTER,
+                variable.name,
+                pack(value)
+            ));
+        }
+        AST::FunctionDefinition {
+            variable,
+            params,
+            body,
+            keyword_token: _,
+        } => {
+            output.push_str(&format!(
+                "{} {}{}",
+                FUNCTION_DEFINITION_CHARACTER,
+                variable.name,
+                pack_function(params, body, config, functions)
+            ));
+        }
+        AST::Function {
+            params,
+            body,
+            keyword_token: _,
+        } => {
+            output.push_str(&format!(
+                "{}{}",
+                FUNCTION_DEFINITION_CHARACTER,
+                pack_function(params, body, config, functions)
+            ));
+        }
+        AST::FunctionCall { function, args, .. } => {
+            output.push_str(&format!(
+                "{}({})",
+                pack(function),
+                args.iter().map(pack).collect::<Vec<String>>().join(", ")
+            ));
+        }
+        AST::Assignment { variable, value } => {
+            output.push_str(&format!("{} = {}", name(variable), pack(value)));
+        }
+        AST::IndexAssignment { target, value } => {
+            output.push_str(&format!("{} = {}", pack(target), pack(value)));
+        }
+        AST::Return {
+            value,
+            keyword_token: _,
+        } => match value {
+            Some(value) => output.push_str(&format!("{} {}", RETURN_CHARACTER, pack(value))),
+            None => output.push_str(RETURN_CHARACTER),
+        },
+        AST::Equality(left, equality, right) => {
+            output.push_str(&format!("{} {} {}", pack(left), equality, pack(right)));
+        }
+        AST::Term(left, term, right, _) => {
+            output.push_str(&format!("{} {} {}", pack(left), term, pack(right)));
+        }
+        AST::Factor(left, factor, right, _) => {
+            output.push_str(&format!("{} {} {}", pack(left), factor, pack(right)));
+        }
+        AST::Unary(term, value, _) => output.push_str(&format!("{}{}", term, pack(value))),
+        AST::Group(value) => output.push_str(&format!("({})", pack(value))),
+        AST::Number(value) => output.push_str(&value.to_string()),
+        AST::String(value) => output.push_str(&format!("\"{}\"", value)),
+        AST::Identifier(variable) => output.push_str(&name(variable)),
+        AST::List { items } => output.push_str(&format!(
+            "[{}]",
+            items.iter().map(pack).collect::<Vec<String>>().join(", ")
+        )),
+        AST::Map { entries } => output.push_str(&format!(
+            "{{ {} }}",
+            entries
+                .iter()
+                .map(|(key, value)| format!("\"{}\": {}", key, pack(value)))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )),
+        AST::Field {
+            value,
+            name,
+            token: _,
+        } => output.push_str(&format!("{}.{}", pack(value), name)),
+        AST::Index {
+            value,
+            index,
+            token: _,
+        } => output.push_str(&format!("{}[{}]", pack(value), pack(index))),
+        AST::Slice {
+            value,
+            start,
+            end,
+            token: _,
+        } => {
+            let bound = |bound: &Option<Box<AST>>| bound.as_deref().map(pack).unwrap_or_default();
+            output.push_str(&format!("{}[{}:{}]", pack(value), bound(start), bound(end)));
+        }
+        AST::None { keyword_token: _ } => output.push_str(NONE_CHARACTER),
+        AST::Import { path, .. } => {
+            output.push_str(&format!("{} \"{}\"", IMPORT_CHARACTER, path));
+        }
+        AST::Propagate { value, token: _ } => output.push_str(&format!("{}?", pack(value))),
+        AST::Comment { value } => output.push_str(&format!("// {}", value)),
+    }
+
+    output
+}
+
+/// Packs the parameters and body of a function, shared between named and
+/// anonymous functions. Parameters are the function's own names, so they are
+/// never packed.
+fn pack_function(params: &[Token], body: &AST, config: &Config, functions: usize) -> String {
+    format!(
+        "({}) {{\n{}\n}}",
+        params
+            .iter()
+            .map(|token| match &token.token_type {
+                TokenTypes::Identifier { value } => value.to_string(),
+                _ => panic!("Function parameters must be identifiers!"),
+            })
+            .collect::<Vec<String>>()
+            .join(", "),
+        pack_node(body, config, functions + 1)
+            .split('\n')
+            .map(|line| format!("\t{}", line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::run_both, translate::Tokenizer};
+
+    fn packed(source: &str) -> String {
+        let config = Config::default();
+        let mut tokens = Tokenizer::from_string(source.to_string()).tokenize();
+
+        pack(&translate::parse(&mut tokens, &config), &config)
+    }
+
+    fn character(builtin: &str) -> &'static str {
+        Config::default().match_builtin(builtin).unwrap()
+    }
+
+    #[test]
+    fn builtins_are_packed() {
+        let packed = packed("print(len([1]));");
+
+        assert!(packed.contains(character("print")), "{}", packed);
+        assert!(packed.contains(character("len")), "{}", packed);
+        assert!(
+            !packed.contains("print") && !packed.contains("len"),
+            "{}",
+            packed
+        );
+    }
+
+    #[test]
+    fn parameters_named_after_builtins_keep_their_name() {
+        let packed = packed("function total(values) {\n    return len(values);\n};");
+
+        assert_eq!(packed.matches("values").count(), 2, "{}", packed);
+        assert!(!packed.contains(character("values")), "{}", packed);
+        assert!(packed.contains(character("len")), "{}", packed);
+    }
+
+    #[test]
+    fn definitions_named_after_builtins_keep_their_name() {
+        let packed = packed("let len = 1;\nprint(len);");
+
+        assert_eq!(packed.matches("len").count(), 2, "{}", packed);
+        assert!(!packed.contains(character("len")), "{}", packed);
+        assert!(packed.contains(character("print")), "{}", packed);
+    }
+
+    #[test]
+    fn builtins_are_packed_outside_of_the_function_that_shadows_them() {
+        let packed = packed(
+            "function first(values) {\n    return values[0];\n};\nprint(first(values({\"a\": 1})));",
+        );
+
+        assert_eq!(packed.matches("values").count(), 2, "{}", packed);
+        assert_eq!(packed.matches(character("values")).count(), 1, "{}", packed);
+    }
+
+    #[test]
+    fn packed_scripts_run_the_same() {
+        let source = "
+            function total(values) {
+                let sum = 0;
+                map(values, function (value) {
+                    sum = sum + value;
+                });
+                return sum;
+            };
+            let keys = [1, 2, 3];
+            print(total(keys));
+            print(values({\"a\": total(keys)}));
+        ";
+
+        assert_eq!(run_both(&packed(source)), run_both(source));
+        assert_eq!(run_both(source), "6\n[6]\n");
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    fmt::Display,
+    fs,
+    mem::discriminant,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    translate::{Token, Tokenizer},
+    Error,
+};
+
+/// How many tokens are looked past when the tokens of two files stop lining
+/// up, e.g. where `pack` added a semicolon that the original file left out
+const LOOKAHEAD: usize = 8;
+
+/// The first line of every source map file
+const HEADER: &str = "transcript source map";
+
+/// Where a token is in a file. Lines start at 1 and columns at 0, counted in
+/// characters like the columns of a `Token`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl From<&Token> for Span {
+    fn from(token: &Token) -> Self {
+        Span {
+            line: token.line,
+            start: token.start,
+            end: token.end,
+        }
+    }
+}
+
+/// Pairs up where each token is in a generated file with where it is in the
+/// file that it was generated from. Keywords are a single character in packed
+/// files and whole words elsewhere, and `pack` lays the script out again, so
+/// the same token is rarely at the same column in both.
+///
+/// Source maps are written next to the generated file with `.map` on the end
+/// of its name, as a header, the path of the original file, and a line for
+/// each token:
+///
+/// ```text
+/// transcript source map
+/// file: script.script
+/// 1:0-1 1:0-8
+/// ```
+pub struct SourceMap {
+    /// The file that was generated from, if it was read from a file
+    pub file: Option<PathBuf>,
+    /// Spans in the generated file and the original file, in the order that
+    /// they appear in the generated file
+    segments: Vec<(Span, Span)>,
+}
+
+impl SourceMap {
+    /// Maps the tokens of `generated` to the matching tokens of `original`
+    pub fn between(generated: &str, original: &str) -> SourceMap {
+        let generated = Tokenizer::from_string(generated.to_string()).tokenize();
+        let original = Tokenizer::from_string(original.to_string()).tokenize();
+
+        SourceMap {
+            file: None,
+            segments: align(&generated, &original),
+        }
+    }
+
+    /// Finds where a span of the generated file came from in the original file
+    pub fn original(&self, span: Span) -> Option<Span> {
+        find(self.segments.iter().map(|(from, to)| (from, to)), span)
+    }
+
+    /// Finds where a span of the original file ended up in the generated file
+    pub fn generated(&self, span: Span) -> Option<Span> {
+        find(self.segments.iter().map(|(to, from)| (from, to)), span)
+    }
+
+    /// The path of the source map for a generated file
+    pub fn path_for(generated: &Path) -> PathBuf {
+        let mut path = generated.as_os_str().to_owned();
+        path.push(".map");
+
+        PathBuf::from(path)
+    }
+
+    /// Reads the source map written next to `generated`, if there is one. The
+    /// path of the original file is relative to the source map.
+    pub fn load(generated: &Path) -> Result<Option<SourceMap>, Error> {
+        let path = SourceMap::path_for(generated);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => return Ok(None),
+        };
+
+        let invalid = |line: usize| {
+            Error::Config(format!(
+                "{}:{}: not a valid source map",
+                path.display(),
+                line + 1
+            ))
+        };
+
+        let mut lines = contents.lines().enumerate();
+
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            _ => return Err(invalid(0)),
+        }
+
+        let mut map = SourceMap {
+            file: None,
+            segments: Vec::new(),
+        };
+
+        for (number, line) in lines {
+            if let Some(file) = line.strip_prefix("file: ") {
+                map.file = Some(path.parent().unwrap_or(Path::new("")).join(file));
+                continue;
+            }
+
+            let segment = line
+                .split_once(' ')
+                .and_then(|(from, to)| Some((parse_span(from)?, parse_span(to)?)))
+                .ok_or_else(|| invalid(number))?;
+
+            map.segments.push(segment);
+        }
+
+        Ok(Some(map))
+    }
+}
+
+impl Display for SourceMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+
+        if let Some(file) = &self.file {
+            writeln!(f, "file: {}", file.display())?;
+        }
+
+        for (from, to) in &self.segments {
+            writeln!(
+                f,
+                "{}:{}-{} {}:{}-{}",
+                from.line, from.start, from.end, to.line, to.start, to.end
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_span(span: &str) -> Option<Span> {
+    let (line, columns) = span.split_once(':')?;
+    let (start, end) = columns.split_once('-')?;
+
+    Some(Span {
+        line: line.parse().ok()?,
+        start: start.parse().ok()?,
+        end: end.parse().ok()?,
+    })
+}
+
+/// Finds the segment that `span` starts in. Spans between tokens (e.g. the
+/// whitespace that a warning points at) are mapped to the next token on the
+/// same line.
+fn find<'a>(segments: impl Iterator<Item = (&'a Span, &'a Span)>, span: Span) -> Option<Span> {
+    segments
+        .filter(|(from, _)| from.line == span.line && span.start < from.end)
+        .min_by_key(|(from, _)| from.start)
+        .map(|(_, to)| *to)
+}
+
+/// Lines up two lists of tokens, which are the same script written two ways.
+/// Tokens of the same kind are paired in order. Where the kinds differ, the
+/// closest tokens that line up again are found, skipping what is in between.
+fn align(generated: &[Token], original: &[Token]) -> Vec<(Span, Span)> {
+    let same = |a: usize, b: usize| {
+        discriminant(&generated[a].token_type) == discriminant(&original[b].token_type)
+    };
+
+    let mut segments = Vec::new();
+    let (mut a, mut b) = (0, 0);
+
+    while a < generated.len() && b < original.len() {
+        if same(a, b) {
+            segments.push((Span::from(&generated[a]), Span::from(&original[b])));
+            a += 1;
+            b += 1;
+            continue;
+        }
+
+        let skip = (1..=LOOKAHEAD)
+            .flat_map(|distance| (0..=distance).map(move |skip| (skip, distance - skip)))
+            .find(|(skip_a, skip_b)| {
+                a + skip_a < generated.len()
+                    && b + skip_b < original.len()
+                    && same(a + skip_a, b + skip_b)
+            });
+
+        match skip {
+            Some((skip_a, skip_b)) => {
+                a += skip_a;
+                b += skip_b;
+            }
+            None => {
+                a += 1;
+                b += 1;
+            }
+        }
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::{BUILTIN_CHARACTERS, VARIABLE_DEFINITION_CHARACTER};
+
+    fn span(line: usize, start: usize, end: usize) -> Span {
+        Span { line, start, end }
+    }
+
+    #[test]
+    fn the_same_script_maps_to_itself() {
+        let source = "let a = 1;\nprint(a);";
+        let map = SourceMap::between(source, source);
+
+        for token in Tokenizer::from_string(source.to_string()).tokenize() {
+            let span = Span::from(&token);
+
+            assert_eq!(map.original(span), Some(span));
+            assert_eq!(map.generated(span), Some(span));
+        }
+    }
+
+    #[test]
+    fn packed_keywords_and_builtins_map_to_their_words() {
+        let (print, character) = BUILTIN_CHARACTERS
+            .iter()
+            .find(|(builtin, _)| *builtin == "print")
+            .unwrap();
+        let packed = format!(
+            "{} a = 1;\n{}(a);",
+            VARIABLE_DEFINITION_CHARACTER, character
+        );
+        let map = SourceMap::between(&packed, &format!("let a = 1;\n{}(a);", print));
+
+        assert_eq!(map.original(span(1, 0, 1)), Some(span(1, 0, 3)));
+        assert_eq!(map.original(span(1, 2, 3)), Some(span(1, 4, 5)));
+        assert_eq!(map.original(span(2, 0, 1)), Some(span(2, 0, 5)));
+        assert_eq!(map.original(span(2, 2, 3)), Some(span(2, 6, 7)));
+
+        assert_eq!(map.generated(span(1, 0, 3)), Some(span(1, 0, 1)));
+        assert_eq!(map.generated(span(2, 0, 5)), Some(span(2, 0, 1)));
+    }
+
+    #[test]
+    fn tokens_line_up_again_after_a_few_that_differ() {
+        let map = SourceMap::between("x = 1;\ny = 2;", "x = (1);\ny = 2;");
+
+        assert_eq!(map.original(span(1, 4, 5)), Some(span(1, 5, 6)));
+        assert_eq!(map.original(span(2, 0, 1)), Some(span(2, 0, 1)));
+        assert_eq!(map.generated(span(2, 4, 5)), Some(span(2, 4, 5)));
+    }
+
+    #[test]
+    fn tokens_past_more_than_the_lookahead_of_differences_are_not_mapped() {
+        let extra = "(".repeat(LOOKAHEAD + 2);
+        let map = SourceMap::between(&format!("x;\n{}y;", extra), "x;\ny;");
+
+        assert_eq!(map.original(span(1, 0, 1)), Some(span(1, 0, 1)));
+        assert_eq!(map.original(span(2, LOOKAHEAD + 2, LOOKAHEAD + 3)), None);
+        assert_eq!(map.generated(span(2, 0, 1)), None);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{utils::Config, Error};
+
+use super::{
+    Glossary, BUILTIN_CHARACTERS, FUNCTION_DEFINITION_CHARACTER, IMPORT_CHARACTER, NONE_CHARACTER,
+    RETURN_CHARACTER, VARIABLE_DEFINITION_CHARACTER,
+};
+
+/// Whether a script has been packed, i.e. uses the character of a keyword or a
+/// builtin anywhere
+pub fn is_packed(source: &str) -> bool {
+    [
+        VARIABLE_DEFINITION_CHARACTER,
+        FUNCTION_DEFINITION_CHARACTER,
+        RETURN_CHARACTER,
+        NONE_CHARACTER,
+        IMPORT_CHARACTER,
+    ]
+    .iter()
+    .chain(BUILTIN_CHARACTERS.iter().map(|(_, character)| character))
+    .any(|character| source.contains(character))
+}
+
+pub fn unpack(source: String, config: &Config) -> Result<String, Error> {
+    let glossary = Glossary::load(config)?;
+
+    if let Some(glossary) = &glossary {
+        glossary.check_translation(&source, config)?;
+    }
+
+    Ok(unpack_with_glossary(source, config, glossary.as_ref()))
+}
+
+/// Unpacks with a glossary that has already been loaded, for unpacking many
+/// small pieces of a script (e.g. each line shown by the debugger). The pieces
+/// are not whole scripts, so the translated names are not checked.
+pub fn unpack_with_glossary(
+    source: String,
+    config: &Config,
+    glossary: Option<&Glossary>,
+) -> String {
+    // Names are translated first, while keywords are still packed and cannot
+    // be mistaken for names
+    let source = match glossary {
+        Some(glossary) => glossary.translate(source),
+        None => source,
+    };
+
+    let source = BUILTIN_CHARACTERS
+        .iter()
+        .fold(source, |source, (builtin, character)| {
+            source.replace(character, config.builtin_name(builtin))
+        });
+
+    source
+        .replace(VARIABLE_DEFINITION_CHARACTER, &config.variable_keyword)
+        .replace(FUNCTION_DEFINITION_CHARACTER, &config.function_keyword)
+        .replace(RETURN_CHARACTER, &config.return_keyword)
+        .replace(NONE_CHARACTER, &config.none_keyword)
+        .replace(IMPORT_CHARACTER, &config.import_keyword)
+}
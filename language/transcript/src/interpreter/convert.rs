@@ -0,0 +1,144 @@
+use crate::{executor::Value, Error};
+
+/// Converts a script value into a rust type, e.g. for the arguments of a
+/// function registered with `Interpreter::register`
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, Error>;
+}
+
+/// Converts a rust type into a script value, e.g. for the result of a function
+/// registered with `Interpreter::register`
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+fn expected(expected: &str, value: &Value) -> Error {
+    Error::Conversion(format!("Expected {}, got {}", expected, value))
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        Ok(value.clone())
+    }
+}
+
+impl FromValue for f32 {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Number(number) => Ok(*number),
+            _ => Err(expected("a number", value)),
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        f32::from_value(value).map(f64::from)
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Number(number) if number.fract() == 0.0 => Ok(*number as i64),
+            _ => Err(expected("a whole number", value)),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Boolean(boolean) => Ok(*boolean),
+            _ => Err(expected("true or false", value)),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::String(string) => Ok(string.to_string()),
+            _ => Err(expected("a string", value)),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Option(Some(value)) => Ok(Some(T::from_value(value)?)),
+            Value::Option(None) => Ok(None),
+            _ => Err(expected("an option", value)),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::List(items) => items.borrow().iter().map(T::from_value).collect(),
+            _ => Err(expected("a list", value)),
+        }
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Option(None)
+    }
+}
+
+impl IntoValue for f32 {
+    fn into_value(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::Number(self as f32)
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Value::Number(self as f32)
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Boolean(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        Value::Option(self.map(|value| Box::new(value.into_value())))
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::list(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{executor::Value, Error};
+
+use super::{FromValue, IntoValue};
+
+/// A rust closure that can be registered with `Interpreter::register`. This is
+/// implemented for closures that take up to four arguments implementing
+/// `FromValue` and return something implementing `IntoValue`.
+pub trait HostFunction<Args> {
+    fn arity(&self) -> usize;
+    fn call(&self, args: Vec<Value>) -> Result<Value, Error>;
+}
+
+macro_rules! host_function {
+    ($($type:ident $name:ident),*) => {
+        impl<F, R, $($type),*> HostFunction<($($type,)*)> for F
+        where
+            F: Fn($($type),*) -> R,
+            R: IntoValue,
+            $($type: FromValue),*
+        {
+            fn arity(&self) -> usize {
+                let names: &[&str] = &[$(stringify!($name)),*];
+                names.len()
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn call(&self, args: Vec<Value>) -> Result<Value, Error> {
+                let mut args = args.iter();
+                $(let $name = $type::from_value(args.next().unwrap())?;)*
+
+                Ok(self($($name),*).into_value())
+            }
+        }
+    };
+}
+
+host_function!();
+host_function!(A a);
+host_function!(A a, B b);
+host_function!(A a, B b, C c);
+host_function!(A a, B b, C c, D d);
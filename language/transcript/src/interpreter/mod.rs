@@ -0,0 +1,302 @@
+use std::{
+    cell::RefCell,
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+};
+
+use crate::{
+    executor::{
+        self, call_error, Arity, Environment, LimitError, Limits, RuntimeError, Unwind, Value,
+    },
+    stdlib::{self, Capabilities, Output},
+    translate::{self, ParseError, AST},
+    utils::Config,
+    Error,
+};
+
+pub use self::convert::*;
+pub use self::function::*;
+
+mod convert;
+mod function;
+
+/// Runs scripts inside of another rust program. Every script run by the same
+/// interpreter shares its globals, so functions defined by one script can be
+/// called by later scripts or by the host with `call_function`.
+///
+/// ```ignore
+/// let mut interpreter = Interpreter::new();
+/// interpreter.register("double", |number: f32| number * 2.0);
+/// interpreter.eval_str("function greet(name) { return \"hi \" + name; };")?;
+///
+/// let greeting = interpreter.call_function("greet", vec!["host".into_value()])?;
+/// ```
+///
+/// Scripts only get the builtins that cannot reach outside of the script,
+/// unless the host grants them more with `with_capabilities`, and are limited
+/// by `Limits::default()` unless the host sets its own with `set_limits`. By
+/// default they are also deterministic, with `random` seeded the same way and
+/// `clock` stopped every run.
+///
+/// Every error is returned as an `Error`, including `print` failing to write
+/// to the output. Runtime errors, limits and syntax errors that point at a
+/// token unwind inside of the engine without running the panic hook, so they
+/// are not written to stderr. The parser still raises its other syntax errors
+/// with `panic!`, which the hook does see, so hosts that parse scripts they did
+/// not write may want a hook that ignores them.
+pub struct Interpreter {
+    config: Config,
+    globals: Rc<RefCell<Environment>>,
+    output: Output,
+    limits: Limits,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// Creates an interpreter that uses the english keywords and builtin names
+    pub fn new() -> Interpreter {
+        Interpreter::with_config(Config::default())
+    }
+
+    /// Creates an interpreter that also accepts the localized keywords and
+    /// builtin names from `config`
+    pub fn with_config(config: Config) -> Interpreter {
+        Interpreter::with_capabilities(config, Capabilities::default())
+    }
+
+    /// Creates an interpreter whose scripts can use the builtins that need the
+    /// given capabilities, e.g. `read_file`
+    pub fn with_capabilities(config: Config, capabilities: Capabilities) -> Interpreter {
+        let output = stdlib::stdout();
+        let globals = stdlib::globals(&config, output.clone(), capabilities).contain();
+
+        Interpreter {
+            config,
+            globals,
+            output,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Sets the limits for every script run from now on. Each call to
+    /// `eval_str` or `call_function` gets the full limits to itself.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Sends everything that scripts print to `output` instead of stdout
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        *self.output.borrow_mut() = Box::new(output);
+    }
+
+    /// Makes a rust closure callable from scripts. Arguments are converted
+    /// with `FromValue`, and a script calling the function with a value that
+    /// cannot be converted gets a runtime error. Like the builtins, scripts
+    /// can define names that shadow the function.
+    pub fn register<Args, F: HostFunction<Args> + 'static>(&mut self, name: &str, function: F) {
+        let arity = function.arity();
+        let args: Vec<String> = (0..arity).map(|index| format!("arg{}", index)).collect();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let function_name = name.to_string();
+
+        let builtins = self
+            .globals
+            .borrow()
+            .enclosing()
+            .expect("The globals are inside of the builtins");
+
+        builtins.borrow_mut().add_rust_function(
+            name,
+            &args,
+            Arity::Exactly(arity),
+            move |args, _call| match function.call(args) {
+                Ok(value) => value,
+                Err(error) => call_error(&format!("{}: {}", function_name, error)),
+            },
+        );
+    }
+
+    /// Returns the value of a global, e.g. a variable defined by a script
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.globals.borrow().get(name)
+    }
+
+    /// Runs a script and returns the value of its last statement. Scripts can
+    /// use text keywords, in english or in the language of the config.
+    pub fn eval_str(&mut self, source: &str) -> Result<Value, Error> {
+        let config = &self.config;
+        let mut ast = catch(|| {
+            let mut tokens = translate::Tokenizer::from_string(source.to_string()).tokenize();
+            translate::parse(&mut tokens, config)
+        })
+        .map_err(|error| match error {
+            Error::Runtime { message, .. } => Error::Parse(message),
+            error => error,
+        })?;
+
+        translate::resolve(&mut ast, &self.globals.borrow().scopes()).map_err(Error::Resolve)?;
+
+        let statements = match &ast {
+            AST::Block { statements } => statements,
+            _ => unreachable!("The parser always returns a block"),
+        };
+
+        let globals = &self.globals;
+        catch(|| {
+            executor::enforce(self.limits, || {
+                let mut last = Value::Option(None);
+
+                for statement in statements {
+                    // The resolver makes sure nothing can unwind past the script
+                    last = match executor::execute(statement, globals.clone()) {
+                        Ok(value) | Err(Unwind::Return(value)) => value,
+                    };
+                }
+
+                last
+            })
+        })
+    }
+
+    /// Runs a script that has been packed with `file::pack`
+    pub fn eval_packed(&mut self, packed: &[u8]) -> Result<Value, Error> {
+        let source = std::str::from_utf8(packed)
+            .map_err(|_| Error::Parse(String::from("Packed scripts must be valid UTF-8")))?;
+
+        self.eval_str(source)
+    }
+
+    /// Calls a global function, e.g. one defined by a script that has already
+    /// been run
+    pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        let function = self.get(name).ok_or_else(|| Error::Runtime {
+            message: format!("Undefined function: {}", name),
+            line: None,
+        })?;
+
+        catch(|| executor::enforce(self.limits, || executor::call_function(&function, args)))
+    }
+}
+
+/// Runs `run`, turning any error raised inside the engine into an `Error`
+pub(crate) fn catch<T>(run: impl FnOnce() -> T) -> Result<T, Error> {
+    panic::catch_unwind(AssertUnwindSafe(run)).map_err(|payload| {
+        if let Some(error) = payload.downcast_ref::<LimitError>() {
+            return Error::Limit {
+                limit: error.limit,
+                line: error.token.as_ref().map(|token| token.line),
+            };
+        }
+
+        if let Some(error) = payload.downcast_ref::<ParseError>() {
+            return Error::Parse(
+                [format!("line {}: {}", error.token.line, error.message)]
+                    .into_iter()
+                    .chain(error.help.iter().map(|note| format!("help: {}", note)))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            );
+        }
+
+        if let Some(error) = payload.downcast_ref::<RuntimeError>() {
+            return Error::Runtime {
+                message: error.message.to_string(),
+                line: Some(error.token.line),
+            };
+        }
+
+        let message = payload
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| {
+                payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+            })
+            .unwrap_or_else(|| String::from("Unknown error"));
+
+        Error::Runtime {
+            message,
+            line: None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_can_shadow_builtins() {
+        let mut interpreter = Interpreter::new();
+
+        let value = interpreter
+            .eval_str("let values = [1, 2];\nfunction len(list) { return 42; };\nlen(values);")
+            .unwrap();
+
+        assert_eq!(value.to_string(), "42");
+    }
+
+    #[test]
+    fn later_scripts_can_redefine_globals() {
+        let mut interpreter = Interpreter::new();
+
+        interpreter.eval_str("let x = 1;").unwrap();
+        interpreter.eval_str("let x = x + 1;").unwrap();
+
+        assert_eq!(interpreter.get("x").unwrap().to_string(), "2");
+    }
+
+    #[test]
+    fn registered_functions_can_be_shadowed() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register("double", |number: f32| number * 2.0);
+
+        assert_eq!(interpreter.eval_str("double(2);").unwrap().to_string(), "4");
+        assert_eq!(
+            interpreter
+                .eval_str("function double(number) { return number; };\ndouble(2);")
+                .unwrap()
+                .to_string(),
+            "2"
+        );
+    }
+
+    /// Output that has been closed, like stdout when the reader of a pipe
+    /// exits
+    struct Closed;
+
+    impl Write for Closed {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn output_that_cannot_be_written_is_a_runtime_error() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(Closed);
+
+        match interpreter.eval_str("let x = 1;\nprint(x);") {
+            Err(Error::Runtime { message, line }) => {
+                assert!(message.starts_with("print failed: "), "{}", message);
+                assert_eq!(line, Some(2));
+            }
+            Err(error) => panic!("Expected a runtime error, got {}", error),
+            Ok(_) => panic!("print should fail"),
+        }
+
+        // The interpreter can still be used afterwards
+        assert_eq!(interpreter.eval_str("x + 1;").unwrap().to_string(), "2");
+    }
+}
@@ -0,0 +1,26 @@
+//! Transcript is a scripting language whose keywords, builtins and names can
+//! all be localized. Scripts are stored packed, with keywords replaced by
+//! private use characters, and unpacked in the language of whoever reads them.
+//!
+//! Host applications can run scripts in-process with `Interpreter`. Nothing in
+//! this crate prints on its own: script output goes to the `Output` handle it
+//! is given, failures are returned as `Error`s, and warnings are sent to the
+//! `log` facade for the host to display however it likes.
+
+pub mod executor;
+pub mod file;
+pub mod module;
+pub mod stdlib;
+pub mod translate;
+pub mod utils;
+pub mod vm;
+
+mod error;
+mod interpreter;
+#[cfg(test)]
+mod testing;
+
+pub use error::Error;
+pub use executor::Value;
+pub use interpreter::*;
+pub use utils::Config;
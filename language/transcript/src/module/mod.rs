@@ -0,0 +1,305 @@
+//! Loads scripts along with every module that they import. Imports are found
+//! in the tokens of a script before it is parsed, so that each module can be
+//! run and stored in a global before the script importing it is resolved.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    executor::{self, execute, Environment, Limits, Value},
+    file::{is_packed, unpack, SourceMap},
+    stdlib::{self, Capabilities, Output},
+    translate::{self, import_name, Token, TokenTypes, AST},
+    utils::{show_in, Config},
+    vm, Error,
+};
+
+pub struct Loader<'a> {
+    config: &'a Config,
+    /// Run scripts on the virtual machine rather than the tree walker. Every
+    /// module has to use the same engine, because functions from one engine
+    /// cannot be called by the other
+    vm: bool,
+    /// Modules that have already been run, by their canonical path
+    modules: HashMap<PathBuf, Value>,
+    /// The modules that are currently being loaded, used to detect cycles
+    loading: Vec<PathBuf>,
+    capabilities: Capabilities,
+    limits: Limits,
+    output: Output,
+}
+
+impl<'a> Loader<'a> {
+    pub fn new(config: &'a Config, vm: bool) -> Loader<'a> {
+        Loader {
+            config,
+            vm,
+            modules: HashMap::new(),
+            loading: Vec::new(),
+            capabilities: Capabilities::all(),
+            limits: Limits::default(),
+            output: stdlib::stdout(),
+        }
+    }
+
+    /// Sends everything that scripts print to `output` instead of stdout
+    pub fn set_output(&mut self, output: Output) {
+        self.output = output;
+    }
+
+    /// Limits the builtins that scripts can use. Scripts run from the command
+    /// line are trusted, so they get every capability unless this is called.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    /// Sets the limits for a script and every module that it imports, which
+    /// share one budget
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Runs a script, after running every module that it imports
+    pub fn run(&mut self, path: &Path) -> Result<(), Error> {
+        let path = path.canonicalize().map_err(|error| Error::File {
+            path: path.to_path_buf(),
+            error,
+        })?;
+
+        // Nothing is loading yet, even if an earlier script stopped with a
+        // parse or runtime error part of the way through its imports
+        self.loading.clear();
+        executor::enforce(self.limits, || self.execute(&path))?;
+
+        Ok(())
+    }
+
+    /// Runs a script and returns its top-level definitions as a map
+    fn execute(&mut self, path: &Path) -> Result<Value, Error> {
+        self.loading.push(path.to_path_buf());
+        let module = self.load(path);
+        self.loading.pop();
+
+        module
+    }
+
+    fn load(&mut self, path: &Path) -> Result<Value, Error> {
+        let contents = fs::read_to_string(path).map_err(|error| Error::File {
+            path: path.to_path_buf(),
+            error,
+        })?;
+
+        if is_packed(&contents) {
+            self.show_unpacked(path, &contents)?;
+        }
+
+        let tokenizer = translate::Tokenizer::from_file(path, contents);
+        let mut tokens = tokenizer.tokenize();
+
+        let mut env = stdlib::globals(self.config, self.output.clone(), self.capabilities);
+
+        for (import, token) in imports(&tokens, self.config) {
+            let module = self.import(&import, &token, path)?;
+            env.define(&import_name(&import), module);
+        }
+
+        let mut ast = translate::parse(&mut tokens, self.config);
+        resolve(&mut ast, &env)?;
+
+        let names = definitions(&ast);
+        let env = env.contain();
+
+        let exports: Vec<(String, Value)> = if self.vm {
+            let program = vm::compile(&ast);
+            let globals = vm::run(&program, env);
+
+            names
+                .into_iter()
+                .filter_map(|name| Some((name.to_string(), globals.get(&name)?)))
+                .collect()
+        } else {
+            // The resolver makes sure nothing can unwind past the script
+            let _ = execute(&ast, env.clone());
+
+            names
+                .into_iter()
+                .filter_map(|name| Some((name.to_string(), env.borrow().get(&name)?)))
+                .collect()
+        };
+
+        Ok(Value::map(exports))
+    }
+
+    /// Shows diagnostics about a packed script in the file that it was packed
+    /// from, if it has a source map, or else in the script unpacked in the
+    /// language of the reader
+    fn show_unpacked(&self, path: &Path, contents: &str) -> Result<(), Error> {
+        let original = SourceMap::load(path)?.and_then(|map| {
+            let original = fs::read_to_string(map.file.as_ref()?).ok()?;
+            Some((map, original))
+        });
+
+        let (map, original) = match original {
+            Some(original) => original,
+            None => {
+                let unpacked = unpack(contents.to_string(), self.config)?;
+                (SourceMap::between(contents, &unpacked), unpacked)
+            }
+        };
+
+        show_in(path, map, &original);
+
+        Ok(())
+    }
+
+    /// Loads the module that `from` imports as `import`, running it if it has
+    /// not been run yet
+    fn import(&mut self, import: &str, token: &Token, from: &Path) -> Result<Value, Error> {
+        let path = self.find(import, from).ok_or_else(|| Error::Script {
+            token: token.clone(),
+            message: format!("Could not find a module at \"{}\"", import),
+        })?;
+
+        if let Some(module) = self.modules.get(&path) {
+            return Ok(module.clone());
+        }
+
+        if let Some(start) = self.loading.iter().position(|loading| *loading == path) {
+            let cycle = self.loading[start..]
+                .iter()
+                .chain([&path])
+                .map(|path| path.display().to_string())
+                .collect::<Vec<String>>()
+                .join(" -> ");
+
+            return Err(Error::Script {
+                token: token.clone(),
+                message: format!("Import cycle: {}", cycle),
+            });
+        }
+
+        let module = self.execute(&path)?;
+        self.modules.insert(path, module.clone());
+
+        Ok(module)
+    }
+
+    /// Imports are relative to the script importing them. If there is nothing
+    /// there, each directory in the search path is tried in order
+    fn find(&self, import: &str, from: &Path) -> Option<PathBuf> {
+        let relative = from.parent().map(|directory| directory.join(import));
+
+        relative
+            .into_iter()
+            .chain(
+                self.config
+                    .search_path
+                    .iter()
+                    .map(|directory| Path::new(directory).join(import)),
+            )
+            .find(|path| path.is_file())
+            .and_then(|path| path.canonicalize().ok())
+    }
+}
+
+/// Binds the variables in `ast` to the slots of `env`, failing with every
+/// problem the resolver found
+pub fn resolve(ast: &mut AST, env: &Environment) -> Result<(), Error> {
+    translate::resolve(ast, &env.scopes()).map_err(Error::Resolve)
+}
+
+/// Finds every `import "path"` in a script. This matches the rule used by the
+/// parser, where the import keyword is only an import if it is followed by a
+/// string.
+fn imports(tokens: &[Token], config: &Config) -> Vec<(String, Token)> {
+    tokens
+        .windows(2)
+        .filter_map(|pair| match (&pair[0].token_type, &pair[1].token_type) {
+            (TokenTypes::Identifier { value }, TokenTypes::String { value: path })
+                if config.match_import_keyword(value) =>
+            {
+                Some((path.to_string(), pair[1].clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The names defined at the top level of a script, in the order that they are
+/// defined. These are what other scripts can use when they import it.
+fn definitions(ast: &AST) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    if let AST::Block { statements } = ast {
+        for statement in statements {
+            if let AST::VariableDefinition { variable, .. }
+            | AST::FunctionDefinition { variable, .. } = statement
+            {
+                if !names.contains(&variable.name) {
+                    names.push(variable.name.to_string());
+                }
+            }
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes scripts to a directory of their own, returning the directory
+    fn scripts(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("transcript-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        for (name, source) in files {
+            fs::write(directory.join(name), source).unwrap();
+        }
+
+        directory
+    }
+
+    #[test]
+    fn failed_imports_are_not_reported_as_cycles() {
+        let directory = scripts(
+            "failed-imports",
+            &[
+                ("broken.script", "print(missing);"),
+                ("first.script", "let broken = import \"broken.script\";"),
+                ("second.script", "let broken = import \"broken.script\";"),
+            ],
+        );
+
+        let config = Config::default();
+        let mut loader = Loader::new(&config, false);
+
+        for script in ["first.script", "second.script"] {
+            match loader.run(&directory.join(script)) {
+                Err(Error::Resolve(errors)) => {
+                    assert_eq!(errors[0].message, "Undefined variable: missing")
+                }
+                Err(error) => panic!("{} failed with {}", script, error),
+                Ok(()) => panic!("{} should not run", script),
+            }
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn missing_scripts_are_errors() {
+        let config = Config::default();
+        let mut loader = Loader::new(&config, false);
+
+        assert!(matches!(
+            loader.run(Path::new("does/not/exist.script")),
+            Err(Error::File { .. })
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::executor::{Arity, Environment, Value};
+
+pub fn register(env: &mut Environment) {
+    // Not every string is a number, so to_number returns an option
+    env.add_rust_function("to_number", &["value"], Arity::Exactly(1), |args, _call| {
+        let number = match &args[0] {
+            Value::Number(number) => Some(*number),
+            Value::String(string) => string.trim().parse::<f32>().ok(),
+            _ => None,
+        };
+
+        Value::Option(number.map(|number| Box::new(Value::Number(number))))
+    });
+
+    env.add_rust_function("to_string", &["value"], Arity::Exactly(1), |args, _call| {
+        Value::String(args[0].to_string())
+    });
+
+    env.add_rust_function("type_of", &["value"], Arity::Exactly(1), |args, _call| {
+        Value::String(String::from(match &args[0] {
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::Option(_) => "option",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Function { .. } | Value::RustFunction { .. } | Value::CompiledFunction(_) => {
+                "function"
+            }
+        }))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run_both;
+
+    #[test]
+    fn strings_that_are_numbers_are_converted() {
+        let source = "
+            print(to_number(\"1.5\"));
+            print(to_number(\" 2 \"));
+            print(to_number(3));
+        ";
+
+        assert_eq!(run_both(source), "some(1.5)\nsome(2)\nsome(3)\n");
+    }
+
+    #[test]
+    fn values_that_are_not_numbers_are_none() {
+        let source = "
+            print(to_number(\"one\"));
+            print(to_number(\"\"));
+            print(to_number([1]));
+            print(unwrap_or(to_number(\"1x\"), 0));
+        ";
+
+        assert_eq!(run_both(source), "none\nnone\nnone\n0\n");
+    }
+
+    #[test]
+    fn values_can_be_described() {
+        let source = "
+            print(to_string(1) + to_string([1]));
+            print(type_of(1));
+            print(type_of(print));
+            print(type_of(function () {}));
+        ";
+
+        assert_eq!(run_both(source), "1[1]\nnumber\nfunction\nfunction\n");
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::{self, BufRead, Write};
+
+use crate::executor::{call_error, Arity, Environment, Value};
+
+use super::{string, Capabilities, Output};
+
+pub fn register(env: &mut Environment, output: Output, capabilities: Capabilities) {
+    let print_output = output.clone();
+    env.add_rust_function(
+        "print",
+        &["value"],
+        Arity::Exactly(1),
+        move |args, _call| {
+            writeln!(print_output.borrow_mut(), "{}", args[0])
+                .unwrap_or_else(|error| io_error("print", error));
+            Value::Option(None)
+        },
+    );
+
+    if !capabilities.stdin {
+        return;
+    }
+
+    // Reads a line from stdin, without the newline. Returns none once there is
+    // nothing left to read
+    env.add_rust_function(
+        "input",
+        &["prompt"],
+        Arity::Between(0, 1),
+        move |args, _call| {
+            if let Some(prompt) = args.first() {
+                let mut output = output.borrow_mut();
+                write!(output, "{}", string(prompt, "input"))
+                    .and_then(|_| output.flush())
+                    .unwrap_or_else(|error| io_error("input", error));
+            }
+
+            let mut line = String::new();
+
+            let read = io::stdin()
+                .lock()
+                .read_line(&mut line)
+                .unwrap_or_else(|error| io_error("input", error));
+
+            match read {
+                0 => Value::Option(None),
+                _ => {
+                    let line = line.strip_suffix('\n').unwrap_or(&line);
+                    let line = line.strip_suffix('\r').unwrap_or(line);
+
+                    Value::Option(Some(Box::new(Value::String(line.to_string()))))
+                }
+            }
+        },
+    );
+}
+
+/// Output that cannot be written or input that cannot be read (e.g. because
+/// stdout was closed) stops the script with a runtime error
+fn io_error(function: &str, error: io::Error) -> ! {
+    call_error(&format!("{} failed: {}", function, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run_both;
+
+    #[test]
+    fn print_writes_a_line() {
+        assert_eq!(run_both("print(\"a\");\nprint([1, 2]);"), "a\n[1, 2]\n");
+    }
+}
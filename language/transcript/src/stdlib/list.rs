@@ -0,0 +1,121 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::executor::{call_error, limits, type_name, Arity, Environment, Value};
+
+use super::{list_items, number};
+
+pub fn register(env: &mut Environment) {
+    env.add_rust_function(
+        "push",
+        &["list", "value"],
+        Arity::Exactly(2),
+        |args, _call| {
+            limits::allocate(std::mem::size_of::<Value>());
+            list_items(&args[0], "push")
+                .borrow_mut()
+                .push(args[1].clone());
+            Value::Option(None)
+        },
+    );
+
+    // Popping from an empty list is not an error, so pop returns an option
+    env.add_rust_function("pop", &["list"], Arity::Exactly(1), |args, _call| {
+        Value::Option(list_items(&args[0], "pop").borrow_mut().pop().map(Box::new))
+    });
+
+    env.add_rust_function(
+        "map",
+        &["list", "function"],
+        Arity::Exactly(2),
+        |args, call| {
+            // The list is copied first so that the function can change it
+            let items = list_items(&args[0], "map").borrow().clone();
+
+            Value::list(
+                items
+                    .into_iter()
+                    .map(|item| call.call(&args[1], vec![item]))
+                    .collect(),
+            )
+        },
+    );
+
+    env.add_rust_function(
+        "filter",
+        &["list", "function"],
+        Arity::Exactly(2),
+        |args, call| {
+            let items = list_items(&args[0], "filter").borrow().clone();
+
+            Value::list(
+                items
+                    .into_iter()
+                    .filter(|item| match call.call(&args[1], vec![item.clone()]) {
+                        Value::Boolean(keep) => keep,
+                        value => call_error(&format!(
+                            "filter expects the function to return true or false, got {}",
+                            type_name(&value)
+                        )),
+                    })
+                    .collect(),
+            )
+        },
+    );
+
+    // `range(end)` counts from 0, `range(start, end)` from start. The end is
+    // never included
+    env.add_rust_function(
+        "range",
+        &["start", "end"],
+        Arity::Between(1, 2),
+        |args, _call| {
+            let (start, end) = match args.as_slice() {
+                [end] => (0.0, number(end, "range")),
+                [start, end] => (number(start, "range"), number(end, "range")),
+                _ => unreachable!(),
+            };
+
+            // Counted before the list is built, so that a huge range stops the
+            // script rather than running out of memory
+            let length = (end as i64 - start as i64).max(0) as usize;
+            limits::allocate(length * std::mem::size_of::<Value>());
+
+            Value::List(Rc::new(RefCell::new(
+                (start as i64..end as i64)
+                    .map(|number| Value::Number(number as f32))
+                    .collect(),
+            )))
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{fail_both, run_both};
+
+    #[test]
+    fn lists_can_be_changed_and_transformed() {
+        let source = "
+            let list = range(1, 4);
+            push(list, 4);
+            print(pop(list));
+            print(map(list, function (n) { return n * 2; }));
+            print(filter(list, function (n) { return n == 2; }));
+            print(range(3));
+            print(pop([]));
+        ";
+
+        assert_eq!(
+            run_both(source),
+            "some(4)\n[2, 4, 6]\n[2]\n[0, 1, 2]\nnone\n"
+        );
+    }
+
+    #[test]
+    fn functions_passed_to_map_must_take_one_argument() {
+        assert_eq!(
+            fail_both("map([1], function (a, b) { return a; });").0,
+            "<anonymous> expects 2 arguments, got 1"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use crate::executor::{Arity, Environment, Value};
+
+use super::{map_entries, string};
+
+pub fn register(env: &mut Environment) {
+    env.add_rust_function("keys", &["map"], Arity::Exactly(1), |args, _call| {
+        Value::list(
+            map_entries(&args[0], "keys")
+                .borrow()
+                .iter()
+                .map(|(key, _)| Value::String(key.to_string()))
+                .collect(),
+        )
+    });
+
+    env.add_rust_function("values", &["map"], Arity::Exactly(1), |args, _call| {
+        Value::list(
+            map_entries(&args[0], "values")
+                .borrow()
+                .iter()
+                .map(|(_, value)| value.clone())
+                .collect(),
+        )
+    });
+
+    env.add_rust_function("has", &["map", "key"], Arity::Exactly(2), |args, _call| {
+        let key = string(&args[1], "has");
+
+        Value::Boolean(
+            map_entries(&args[0], "has")
+                .borrow()
+                .iter()
+                .any(|(existing, _)| existing == key),
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run_both;
+
+    #[test]
+    fn maps_can_be_looked_through() {
+        let source = "
+            let map = {\"a\": 1, \"b\": 2};
+            print(keys(map));
+            print(values(map));
+            print(has(map, \"a\"));
+            print(has(map, \"c\"));
+        ";
+
+        assert_eq!(run_both(source), "[a, b]\n[1, 2]\ntrue\nfalse\n");
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::executor::{Arity, Environment, Value};
+
+use super::{number, Capabilities};
+
+/// What `random` is seeded with when scripts are not given the `entropy`
+/// capability
+const FIXED_SEED: u64 = 0;
+
+/// Spreads the bits of a seed out, so that similar seeds (e.g. 1 and 2) give
+/// completely different sequences. This is splitmix64
+fn seed(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+
+    // xorshift gets stuck if its state is ever zero
+    (z ^ (z >> 31)).max(1)
+}
+
+/// Returns a number between 0 and 1 using xorshift64*
+fn random(state: &Cell<u64>) -> f32 {
+    let mut x = state.get();
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    state.set(x);
+
+    // The top 24 bits are all that fit into an f32 without rounding up to 1
+    (x.wrapping_mul(0x2545F4914F6CDD1D) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn numbers(args: &[Value], function: &str) -> Vec<f32> {
+    args.iter().map(|arg| number(arg, function)).collect()
+}
+
+pub fn register(env: &mut Environment, capabilities: Capabilities) {
+    env.add_rust_function("floor", &["number"], Arity::Exactly(1), |args, _call| {
+        Value::Number(number(&args[0], "floor").floor())
+    });
+
+    env.add_rust_function("sqrt", &["number"], Arity::Exactly(1), |args, _call| {
+        Value::Number(number(&args[0], "sqrt").sqrt())
+    });
+
+    env.add_rust_function(
+        "pow",
+        &["base", "exponent"],
+        Arity::Exactly(2),
+        |args, _call| Value::Number(number(&args[0], "pow").powf(number(&args[1], "pow"))),
+    );
+
+    env.add_rust_function("min", &["numbers"], Arity::AtLeast(1), |args, _call| {
+        Value::Number(
+            numbers(&args, "min")
+                .into_iter()
+                .fold(f32::INFINITY, f32::min),
+        )
+    });
+
+    env.add_rust_function("max", &["numbers"], Arity::AtLeast(1), |args, _call| {
+        Value::Number(
+            numbers(&args, "max")
+                .into_iter()
+                .fold(f32::NEG_INFINITY, f32::max),
+        )
+    });
+
+    // Every environment has its own generator, so one script seeding it does
+    // not change the numbers that another script gets
+    let state = Rc::new(Cell::new(seed(if capabilities.entropy {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default()
+    } else {
+        FIXED_SEED
+    })));
+
+    // Returns a number between 0 (inclusive) and 1 (exclusive). Passing a seed
+    // restarts the generator, so the same seed always gives the same numbers
+    env.add_rust_function(
+        "random",
+        &["seed"],
+        Arity::Between(0, 1),
+        move |args, _call| {
+            if let Some(value) = args.first() {
+                let value = number(value, "random");
+                state.set(seed(value.to_bits() as u64));
+            }
+
+            Value::Number(random(&state))
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run_both;
+
+    #[test]
+    fn numbers_can_be_rounded_and_compared() {
+        let source = "
+            print(floor(7 / 2));
+            print(sqrt(9));
+            print(pow(2, 3));
+            print(min(3, 1, 2));
+            print(max(3, 1, 2));
+        ";
+
+        assert_eq!(run_both(source), "3\n3\n8\n1\n3\n");
+    }
+
+    #[test]
+    fn seeding_random_restarts_it() {
+        let source = "
+            let first = random(5);
+            let second = random();
+            print(first == random(5));
+            print(second == random());
+            print(first == second);
+            print(random(1) == random(2));
+        ";
+
+        assert_eq!(run_both(source), "true\ntrue\nfalse\nfalse\n");
+    }
+
+    #[test]
+    fn random_is_the_same_every_run_without_entropy() {
+        let source = "print(random());\nprint(random());";
+
+        assert_eq!(run_both(source), run_both(source));
+    }
+
+    #[test]
+    fn random_is_between_zero_and_one() {
+        let source = "
+            let low = 1;
+            let high = 0;
+            map(range(1000), function (n) {
+                let number = random();
+                low = min(low, number);
+                high = max(high, number);
+            });
+            print(low == min(low, 0));
+            print(high == max(high, 1));
+        ";
+
+        assert_eq!(run_both(source), "false\nfalse\n");
+    }
+}
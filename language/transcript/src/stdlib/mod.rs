@@ -0,0 +1,234 @@
+//! The builtin functions that every script can use. Each group of functions
+//! registers itself with `Environment::add_rust_function`.
+
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use crate::{
+    executor::{call_error, type_name, Environment, Value},
+    file::BUILTIN_CHARACTERS,
+    utils::Config,
+};
+
+mod convert;
+mod io;
+mod list;
+mod map;
+mod math;
+mod option;
+mod string;
+mod system;
+
+/// Where `print` writes to. It is shared, so that whoever is running a script
+/// can change it after the environment has been created
+pub type Output = Rc<RefCell<Box<dyn Write>>>;
+
+pub fn stdout() -> Output {
+    Rc::new(RefCell::new(Box::new(std::io::stdout())))
+}
+
+/// Builtins that can reach outside of the script. Each one is off by default,
+/// so that scripts that cannot be trusted only get the builtins that the host
+/// has opted in to.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Capabilities {
+    /// `input`
+    pub stdin: bool,
+    /// `read_file` and `write_file`
+    pub files: bool,
+    /// `get_env`
+    pub env: bool,
+    /// `clock` reads the real time, rather than a clock that never moves
+    pub clock: bool,
+    /// `random` is seeded from the time, rather than with the same seed every
+    /// run
+    pub entropy: bool,
+}
+
+impl Capabilities {
+    /// Every capability, for scripts that are trusted
+    pub fn all() -> Capabilities {
+        Capabilities {
+            stdin: true,
+            files: true,
+            env: true,
+            clock: true,
+            entropy: true,
+        }
+    }
+
+    /// Every capability except the ones that make scripts give different
+    /// output each time they run, for golden tests of script output. Files and
+    /// environment variables change between runs as well, so only `input` is
+    /// left, which reads whatever the test gives the script.
+    pub fn deterministic() -> Capabilities {
+        Capabilities {
+            stdin: true,
+            ..Capabilities::default()
+        }
+    }
+}
+
+/// Creates the global environment that scripts are run in. Builtins that have
+/// been given a localized name in the `[builtins]` section of transcript.ini
+/// can be called by either name, as well as by their packed character.
+pub fn environment(config: &Config, output: Output, capabilities: Capabilities) -> Environment {
+    let mut env = Environment::new();
+
+    io::register(&mut env, output, capabilities);
+    option::register(&mut env);
+    list::register(&mut env);
+    map::register(&mut env);
+    string::register(&mut env);
+    math::register(&mut env, capabilities);
+    convert::register(&mut env);
+    system::register(&mut env, capabilities);
+
+    for (name, character) in BUILTIN_CHARACTERS {
+        if let Some(builtin) = env.get(name) {
+            env.define(character, builtin);
+        }
+    }
+
+    for (name, localized) in &config.builtin_names {
+        if let Some(builtin) = env.get(name) {
+            env.define(localized, builtin);
+        }
+    }
+
+    env
+}
+
+/// Creates the environment that a script's own definitions go in. The builtins
+/// are in the scope around it, so that scripts can define names that are the
+/// same as a builtin (e.g. `let values = ...`), which shadow the builtin.
+pub fn globals(config: &Config, output: Output, capabilities: Capabilities) -> Environment {
+    Environment::from_enclosing(environment(config, output, capabilities).contain())
+}
+
+fn number(value: &Value, function: &str) -> f32 {
+    match value {
+        Value::Number(number) => *number,
+        _ => call_error(&format!(
+            "{} expects a number, got {}",
+            function,
+            type_name(value)
+        )),
+    }
+}
+
+fn string<'a>(value: &'a Value, function: &str) -> &'a str {
+    match value {
+        Value::String(string) => string,
+        _ => call_error(&format!(
+            "{} expects a string, got {}",
+            function,
+            type_name(value)
+        )),
+    }
+}
+
+fn list_items<'a>(value: &'a Value, function: &str) -> &'a Rc<RefCell<Vec<Value>>> {
+    match value {
+        Value::List(items) => items,
+        _ => call_error(&format!(
+            "{} expects a list, got {}",
+            function,
+            type_name(value)
+        )),
+    }
+}
+
+fn map_entries<'a>(value: &'a Value, function: &str) -> &'a Rc<RefCell<Vec<(String, Value)>>> {
+    match value {
+        Value::Map(entries) => entries,
+        _ => call_error(&format!(
+            "{} expects a map, got {}",
+            function,
+            type_name(value)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::fail_both;
+
+    /// Calls to builtins with the wrong number or types of arguments, and the
+    /// error that each one fails with
+    const WRONG_ARGUMENTS: &[(&str, &str)] = &[
+        // convert
+        ("to_number();", "to_number expects 1 argument, got 0"),
+        ("type_of(1, 2);", "type_of expects 1 argument, got 2"),
+        // io
+        ("print();", "print expects 1 argument, got 0"),
+        ("print(1, 2);", "print expects 1 argument, got 2"),
+        (
+            "input(\"a\", \"b\");",
+            "input expects 0 to 1 arguments, got 2",
+        ),
+        ("input(1);", "input expects a string, got a number"),
+        // list
+        ("push([]);", "push expects 2 arguments, got 1"),
+        ("range(1, 2, 3);", "range expects 1 to 2 arguments, got 3"),
+        ("pop(1);", "pop expects a list, got a number"),
+        ("range(\"a\");", "range expects a number, got a string"),
+        (
+            "filter([1], function (n) { return n; });",
+            "filter expects the function to return true or false, got a number",
+        ),
+        // map
+        ("keys();", "keys expects 1 argument, got 0"),
+        ("has({});", "has expects 2 arguments, got 1"),
+        ("values([1]);", "values expects a map, got a list"),
+        ("has({}, 1);", "has expects a string, got a number"),
+        // math
+        ("pow(2);", "pow expects 2 arguments, got 1"),
+        ("min();", "min expects at least 1 arguments, got 0"),
+        ("random(1, 2);", "random expects 0 to 1 arguments, got 2"),
+        ("floor(\"1\");", "floor expects a number, got a string"),
+        ("max(1, none);", "max expects a number, got an option"),
+        ("random(\"seed\");", "random expects a number, got a string"),
+        // option
+        ("some();", "some expects 1 argument, got 0"),
+        ("unwrap_or(none);", "unwrap_or expects 2 arguments, got 1"),
+        ("is_none(1);", "is_none expects an option, got a number"),
+        (
+            "unwrap_or(\"a\", 1);",
+            "unwrap_or expects an option, got a string",
+        ),
+        // string
+        ("len();", "len expects 1 argument, got 0"),
+        ("format();", "format expects at least 1 arguments, got 0"),
+        (
+            "replace(\"a\", \"b\");",
+            "replace expects 3 arguments, got 2",
+        ),
+        (
+            "len(1);",
+            "len expects a string, a list or a map, got a number",
+        ),
+        ("upper([]);", "upper expects a string, got a list"),
+        ("join(\"a\", \",\");", "join expects a list, got a string"),
+        ("format(1);", "format expects a string, got a number"),
+        // system
+        ("clock(1);", "clock expects 0 arguments, got 1"),
+        (
+            "write_file(\"a\");",
+            "write_file expects 2 arguments, got 1",
+        ),
+        ("read_file(1);", "read_file expects a string, got a number"),
+        ("get_env([]);", "get_env expects a string, got a list"),
+    ];
+
+    #[test]
+    fn builtins_check_their_arguments() {
+        for (source, message) in WRONG_ARGUMENTS {
+            assert_eq!(
+                fail_both(source),
+                (message.to_string(), Some(1)),
+                "{}",
+                source
+            );
+        }
+    }
+}
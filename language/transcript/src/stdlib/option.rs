@@ -0,0 +1,51 @@
+use crate::executor::{call_error, type_name, Arity, Environment, Value};
+
+pub fn register(env: &mut Environment) {
+    env.add_rust_function("some", &["value"], Arity::Exactly(1), |args, _call| {
+        Value::Option(Some(Box::new(args[0].clone())))
+    });
+
+    env.add_rust_function(
+        "is_none",
+        &["option"],
+        Arity::Exactly(1),
+        |args, _call| match &args[0] {
+            Value::Option(option) => Value::Boolean(option.is_none()),
+            value => call_error(&format!(
+                "is_none expects an option, got {}",
+                type_name(value)
+            )),
+        },
+    );
+
+    env.add_rust_function(
+        "unwrap_or",
+        &["option", "default"],
+        Arity::Exactly(2),
+        |args, _call| match &args[0] {
+            Value::Option(Some(value)) => *value.clone(),
+            Value::Option(None) => args[1].clone(),
+            value => call_error(&format!(
+                "unwrap_or expects an option, got {}",
+                type_name(value)
+            )),
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::run_both;
+
+    #[test]
+    fn options_can_be_checked_and_unwrapped() {
+        let source = "
+            print(is_none(none));
+            print(is_none(some(1)));
+            print(unwrap_or(some(1), 2));
+            print(unwrap_or(none, 2));
+        ";
+
+        assert_eq!(run_both(source), "true\nfalse\n1\n2\n");
+    }
+}
@@ -0,0 +1,140 @@
+use crate::executor::{call_error, type_name, Arity, Environment, Value};
+
+use super::{list_items, string};
+
+pub fn register(env: &mut Environment) {
+    env.add_rust_function(
+        "len",
+        &["value"],
+        Arity::Exactly(1),
+        |args, _call| match &args[0] {
+            Value::String(string) => Value::Number(string.chars().count() as f32),
+            Value::List(items) => Value::Number(items.borrow().len() as f32),
+            Value::Map(entries) => Value::Number(entries.borrow().len() as f32),
+            value => call_error(&format!(
+                "len expects a string, a list or a map, got {}",
+                type_name(value)
+            )),
+        },
+    );
+
+    env.add_rust_function("upper", &["string"], Arity::Exactly(1), |args, _call| {
+        Value::String(string(&args[0], "upper").to_uppercase())
+    });
+
+    env.add_rust_function("lower", &["string"], Arity::Exactly(1), |args, _call| {
+        Value::String(string(&args[0], "lower").to_lowercase())
+    });
+
+    env.add_rust_function("trim", &["string"], Arity::Exactly(1), |args, _call| {
+        Value::String(string(&args[0], "trim").trim().to_string())
+    });
+
+    env.add_rust_function(
+        "split",
+        &["string", "separator"],
+        Arity::Exactly(2),
+        |args, _call| {
+            Value::list(
+                string(&args[0], "split")
+                    .split(string(&args[1], "split"))
+                    .map(|part| Value::String(part.to_string()))
+                    .collect(),
+            )
+        },
+    );
+
+    env.add_rust_function(
+        "join",
+        &["list", "separator"],
+        Arity::Exactly(2),
+        |args, _call| {
+            Value::String(
+                list_items(&args[0], "join")
+                    .borrow()
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(string(&args[1], "join")),
+            )
+        },
+    );
+
+    env.add_rust_function(
+        "replace",
+        &["string", "from", "to"],
+        Arity::Exactly(3),
+        |args, _call| {
+            Value::String(
+                string(&args[0], "replace")
+                    .replace(string(&args[1], "replace"), string(&args[2], "replace")),
+            )
+        },
+    );
+
+    // Replaces each `{}` in the template with the next value
+    env.add_rust_function(
+        "format",
+        &["template", "values"],
+        Arity::AtLeast(1),
+        |args, _call| {
+            let mut parts = string(&args[0], "format").split("{}");
+            let mut values = args[1..].iter();
+            let mut output = parts.next().unwrap_or_default().to_string();
+
+            for part in parts {
+                match values.next() {
+                    Some(value) => output.push_str(&value.to_string()),
+                    None => call_error("format has more {} than values"),
+                }
+
+                output.push_str(part);
+            }
+
+            if values.next().is_some() {
+                call_error("format has more values than {}");
+            }
+
+            Value::String(output)
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{fail_both, run_both};
+
+    #[test]
+    fn strings_can_be_changed() {
+        let source = "
+            print(len(\"héllo\"));
+            print(upper(\"a\") + lower(\"B\") + trim(\"  c  \"));
+            print(split(\"a,b\", \",\"));
+            print(join([1, 2], \"-\"));
+            print(replace(\"aa\", \"a\", \"b\"));
+        ";
+
+        assert_eq!(run_both(source), "5\nAbc\n[a, b]\n1-2\nbb\n");
+    }
+
+    #[test]
+    fn format_replaces_each_placeholder() {
+        assert_eq!(
+            run_both("print(format(\"{} + {} = {}\", 1, 2, 3));"),
+            "1 + 2 = 3\n"
+        );
+        assert_eq!(run_both("print(format(\"none\"));"), "none\n");
+    }
+
+    #[test]
+    fn format_needs_a_value_for_every_placeholder() {
+        assert_eq!(
+            fail_both("format(\"{} and {}\", 1);").0,
+            "format has more {} than values"
+        );
+        assert_eq!(
+            fail_both("format(\"{}\", 1, 2);").0,
+            "format has more values than {}"
+        );
+    }
+}
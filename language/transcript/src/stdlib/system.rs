@@ -0,0 +1,106 @@
+use std::{env, fs, time::Instant};
+
+use crate::executor::{Arity, Environment, Value};
+
+use super::{string, Capabilities};
+
+/// Builtins that reach outside of the script. Most are only registered for the
+/// capabilities that the host has granted
+pub fn register(env: &mut Environment, capabilities: Capabilities) {
+    // Returns the number of seconds since the script started. Without the
+    // clock capability this is always 0, so scripts that time themselves
+    // still print the same thing every run
+    let start = Instant::now();
+    env.add_rust_function("clock", &[], Arity::Exactly(0), move |_args, _call| {
+        Value::Number(match capabilities.clock {
+            true => start.elapsed().as_secs_f32(),
+            false => 0.0,
+        })
+    });
+
+    if capabilities.files {
+        // Returns none if the file cannot be read, e.g. because it does not
+        // exist
+        env.add_rust_function("read_file", &["path"], Arity::Exactly(1), |args, _call| {
+            Value::Option(
+                fs::read_to_string(string(&args[0], "read_file"))
+                    .ok()
+                    .map(|contents| Box::new(Value::String(contents))),
+            )
+        });
+
+        // Returns whether the file was written
+        env.add_rust_function(
+            "write_file",
+            &["path", "contents"],
+            Arity::Exactly(2),
+            |args, _call| {
+                Value::Boolean(
+                    fs::write(
+                        string(&args[0], "write_file"),
+                        string(&args[1], "write_file"),
+                    )
+                    .is_ok(),
+                )
+            },
+        );
+    }
+
+    if capabilities.env {
+        env.add_rust_function("get_env", &["name"], Arity::Exactly(1), |args, _call| {
+            Value::Option(
+                env::var(string(&args[0], "get_env"))
+                    .ok()
+                    .map(|value| Box::new(Value::String(value))),
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        stdlib::Capabilities,
+        testing::{run_both, run_with_capabilities},
+        Error,
+    };
+
+    #[test]
+    fn deterministic_scripts_print_the_same_thing_every_run() {
+        let source = "
+            let start = clock();
+            print(format(\"{} {}\", random(), random()));
+            print(format(\"took {}\", clock() - start));
+        ";
+
+        for vm in [false, true] {
+            let run = || run_with_capabilities(source, vm, Capabilities::deterministic()).unwrap();
+
+            assert_eq!(run(), run());
+        }
+    }
+
+    #[test]
+    fn deterministic_scripts_cannot_use_files_or_the_environment() {
+        for source in [
+            "read_file(\"a\");",
+            "write_file(\"a\", \"b\");",
+            "get_env(\"HOME\");",
+        ] {
+            assert!(matches!(
+                run_with_capabilities(source, false, Capabilities::deterministic()),
+                Err(Error::Resolve(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn the_clock_is_stopped_without_the_clock_capability() {
+        assert_eq!(run_both("print(clock());"), "0\n");
+    }
+
+    #[test]
+    fn files_that_cannot_be_read_are_none() {
+        assert_eq!(run_both("print(read_file(\"does/not/exist\"));"), "none\n");
+    }
+}
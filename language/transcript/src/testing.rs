@@ -0,0 +1,148 @@
+//! Helpers for tests that run whole scripts
+
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use crate::{
+    executor::{self, Limit, Limits},
+    interpreter,
+    module::resolve,
+    stdlib::{self, Capabilities},
+    translate::{self, Tokenizer},
+    utils::Config,
+    vm, Error,
+};
+
+/// Everything that a script printed
+#[derive(Clone, Default)]
+struct Printed(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Printed {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs a script on the tree walker, or on the virtual machine if `vm` is set,
+/// returning what it printed. Scripts are deterministic, but can read and
+/// write files and the environment so that those builtins can be tested.
+pub fn run(source: &str, vm: bool) -> Result<String, Error> {
+    run_with_limits(source, vm, Limits::default())
+}
+
+/// Runs a script like `run`, with `limits` enforced
+pub fn run_with_limits(source: &str, vm: bool, limits: Limits) -> Result<String, Error> {
+    let capabilities = Capabilities {
+        files: true,
+        env: true,
+        ..Capabilities::deterministic()
+    };
+
+    run_with(source, vm, limits, capabilities)
+}
+
+/// Runs a script like `run`, with only the builtins that `capabilities` grant
+pub fn run_with_capabilities(
+    source: &str,
+    vm: bool,
+    capabilities: Capabilities,
+) -> Result<String, Error> {
+    run_with(source, vm, Limits::default(), capabilities)
+}
+
+fn run_with(
+    source: &str,
+    vm: bool,
+    limits: Limits,
+    capabilities: Capabilities,
+) -> Result<String, Error> {
+    let config = Config::default();
+    let printed = Printed::default();
+    let output: stdlib::Output = Rc::new(RefCell::new(Box::new(printed.clone())));
+    let globals = stdlib::globals(&config, output, capabilities);
+
+    let mut ast = interpreter::catch(|| {
+        let mut tokens = Tokenizer::from_string(source.to_string()).tokenize();
+        translate::parse(&mut tokens, &config)
+    })?;
+    resolve(&mut ast, &globals)?;
+
+    let globals = globals.contain();
+    interpreter::catch(|| {
+        executor::enforce(limits, || match vm {
+            true => {
+                vm::run(&vm::compile(&ast), globals);
+            }
+            false => {
+                let _ = executor::execute(&ast, globals);
+            }
+        })
+    })?;
+
+    let printed = printed.0.borrow();
+    Ok(String::from_utf8_lossy(&printed).to_string())
+}
+
+/// Runs a script on both engines, checking that they print the same thing, and
+/// returns what they printed
+pub fn run_both(source: &str) -> String {
+    let tree_walker = run(source, false).unwrap();
+    let virtual_machine = run(source, true).unwrap();
+
+    assert_eq!(
+        tree_walker, virtual_machine,
+        "The tree walker and the virtual machine printed different things"
+    );
+
+    tree_walker
+}
+
+/// Runs a script that should fail on both engines, checking that they fail
+/// with the same runtime error, and returns its message and line
+pub fn fail_both(source: &str) -> (String, Option<usize>) {
+    let error = |vm| match run(source, vm) {
+        Err(Error::Runtime { message, line }) => (message, line),
+        Err(error) => panic!("Expected a runtime error, got {}", error),
+        Ok(printed) => panic!(
+            "Expected a runtime error, but the script printed {:?}",
+            printed
+        ),
+    };
+
+    let tree_walker = error(false);
+    let virtual_machine = error(true);
+
+    assert_eq!(
+        tree_walker, virtual_machine,
+        "The tree walker and the virtual machine failed differently"
+    );
+
+    tree_walker
+}
+
+/// Runs a script that should exceed `limits` on both engines, checking that
+/// they stop on the same line, and returns the limit and the line
+pub fn exceed_both(source: &str, limits: Limits) -> (Limit, Option<usize>) {
+    let error = |vm| match run_with_limits(source, vm, limits) {
+        Err(Error::Limit { limit, line }) => (limit, line),
+        Err(error) => panic!("Expected a limit to be exceeded, got {}", error),
+        Ok(printed) => panic!(
+            "Expected a limit to be exceeded, but the script printed {:?}",
+            printed
+        ),
+    };
+
+    let tree_walker = error(false);
+    let virtual_machine = error(true);
+
+    assert_eq!(
+        tree_walker, virtual_machine,
+        "The tree walker and the virtual machine stopped differently"
+    );
+
+    tree_walker
+}
@@ -1,9 +1,13 @@
+use std::{path::Path, sync::Arc};
+
 mod parse;
+mod resolve;
 /// This module is responsible for translating a specified file into a tokenized
 /// file and back.
 mod tokenizer;
 
 pub use parse::*;
+pub use resolve::*;
 pub use tokenizer::*;
 
 #[derive(Clone, Debug)]
@@ -13,6 +17,8 @@ pub struct Token {
     pub end: usize,
     pub line: usize,
     pub line_contents: String,
+    /// The file that the token was read from, if it came from a file
+    pub path: Option<Arc<Path>>,
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -29,21 +35,25 @@ pub enum TokenTypes {
     Star,
     Slash,
     Equals,
+    EqualEqual,
+    BangEqual,
     Comma,
+    Colon,
+    Dot,
+    Question,
 
     OpenParen,
     CloseParen,
     OpenCurly,
     CloseCurly,
+    OpenBracket,
+    CloseBracket,
 
     Semi,
 }
 
 impl TokenTypes {
     pub fn is_identifier(&self) -> bool {
-        match self {
-            TokenTypes::Identifier { .. } => true,
-            _ => false,
-        }
+        matches!(self, TokenTypes::Identifier { .. })
     }
 }
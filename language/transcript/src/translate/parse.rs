@@ -0,0 +1,1281 @@
+use std::{cell::RefCell, fmt::Display};
+
+use crate::{
+    file::RETURN_CHARACTER,
+    utils::{suggest_keyword, Config},
+};
+
+use super::{Token, TokenTypes};
+
+#[allow(clippy::upper_case_acronyms)]
+type BAST = Box<AST>;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone)]
+pub enum AST {
+    Block {
+        statements: Vec<AST>,
+    },
+    VariableDefinition {
+        variable: Variable,
+        value: BAST,
+        keyword_token: Token,
+    },
+    FunctionDefinition {
+        variable: Variable,
+        params: Vec<Token>,
+        body: BAST,
+        keyword_token: Token,
+    },
+    /// A function without a name, used as a value
+    Function {
+        params: Vec<Token>,
+        body: BAST,
+        keyword_token: Token,
+    },
+    /// The token is the function being called, or the opening bracket of the
+    /// arguments if the function is not named (e.g. `make_adder(1)(2)`), for
+    /// reporting errors in the call against
+    FunctionCall {
+        function: BAST,
+        args: Vec<AST>,
+        token: Token,
+    },
+    Assignment {
+        variable: Variable,
+        value: BAST,
+    },
+    /// Assigning to an item of a list or map. The target is always an
+    /// `Index` or a `Field`
+    IndexAssignment {
+        target: BAST,
+        value: BAST,
+    },
+    Return {
+        value: Option<BAST>,
+        keyword_token: Token,
+    },
+    Comment {
+        value: String,
+    },
+
+    // Expression symbols. The tokens of terms, factors and unary operators
+    // are the operators, which runtime errors are reported against
+    Equality(BAST, EqualitySymbol, BAST),
+    Term(BAST, TermSymbol, BAST, Token),
+    Factor(BAST, FactorSymbol, BAST, Token),
+    Unary(TermSymbol, BAST, Token),
+    Group(BAST),
+    Number(f32),
+    String(String),
+    Identifier(Variable),
+    List {
+        items: Vec<AST>,
+    },
+    /// `value[index]`. The token covers the index expression, so that runtime
+    /// errors can point at it
+    Index {
+        value: BAST,
+        index: BAST,
+        token: Token,
+    },
+    /// `{ "key": value }`
+    Map {
+        entries: Vec<(String, AST)>,
+    },
+    /// `value.name`, the same as `value["name"]`
+    Field {
+        value: BAST,
+        name: String,
+        token: Token,
+    },
+    /// `value[start:end]`, where either bound can be left out
+    Slice {
+        value: BAST,
+        start: Option<BAST>,
+        end: Option<BAST>,
+        token: Token,
+    },
+    None {
+        keyword_token: Token,
+    },
+    /// `import "path"`, which evaluates to the top-level definitions of
+    /// another script. The module is loaded before the script runs and stored
+    /// in a global, see `import_name`
+    Import {
+        path: String,
+        variable: Variable,
+        keyword_token: Token,
+    },
+    /// Unwraps an option, returning none from the current function if there
+    /// is nothing inside of it
+    Propagate {
+        value: BAST,
+        token: Token,
+    },
+}
+
+/// Where a variable lives at runtime. `depth` is the number of environments
+/// that have to be walked up from the one the variable is used in, and `slot`
+/// is the index of the variable inside of that environment.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Binding {
+    pub depth: usize,
+    pub slot: usize,
+}
+
+impl AST {
+    /// The first token in this node, used to find where a statement is in
+    /// the source. Literals do not keep their tokens, so nodes made only of
+    /// literals have none.
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            AST::VariableDefinition { keyword_token, .. }
+            | AST::FunctionDefinition { keyword_token, .. }
+            | AST::Function { keyword_token, .. }
+            | AST::Return { keyword_token, .. }
+            | AST::None { keyword_token }
+            | AST::Import { keyword_token, .. } => Some(keyword_token),
+            AST::Assignment { variable, .. } | AST::Identifier(variable) => Some(&variable.token),
+            AST::FunctionCall {
+                function: value, ..
+            }
+            | AST::IndexAssignment { target: value, .. }
+            | AST::Index { value, .. }
+            | AST::Field { value, .. }
+            | AST::Slice { value, .. }
+            | AST::Propagate { value, .. }
+            | AST::Group(value) => value.token(),
+            AST::Unary(_, _, token) => Some(token),
+            AST::Equality(left, _, right) => left.token().or_else(|| right.token()),
+            AST::Term(left, _, right, token) | AST::Factor(left, _, right, token) => {
+                left.token().or_else(|| right.token()).or(Some(token))
+            }
+            AST::List { items } => items.iter().find_map(AST::token),
+            AST::Map { entries } => entries.iter().find_map(|(_, value)| value.token()),
+            AST::Block { statements } => statements.iter().find_map(AST::token),
+            AST::Comment { .. } | AST::Number(_) | AST::String(_) => None,
+        }
+    }
+
+    /// Every name that this node defines or uses, in the order that they are
+    /// written. Function parameters are tokens rather than variables, so they
+    /// are left out.
+    pub fn variables(&self) -> Vec<&Variable> {
+        let mut variables = Vec::new();
+        self.collect_variables(&mut variables);
+        variables
+    }
+
+    fn collect_variables<'a>(&'a self, variables: &mut Vec<&'a Variable>) {
+        match self {
+            AST::Block { statements } => {
+                for statement in statements {
+                    statement.collect_variables(variables);
+                }
+            }
+            AST::VariableDefinition {
+                variable, value, ..
+            }
+            | AST::Assignment { variable, value } => {
+                variables.push(variable);
+                value.collect_variables(variables);
+            }
+            AST::FunctionDefinition { variable, body, .. } => {
+                variables.push(variable);
+                body.collect_variables(variables);
+            }
+            AST::Function { body, .. } => body.collect_variables(variables),
+            AST::FunctionCall { function, args, .. } => {
+                function.collect_variables(variables);
+
+                for arg in args {
+                    arg.collect_variables(variables);
+                }
+            }
+            AST::IndexAssignment {
+                target: left,
+                value: right,
+            }
+            | AST::Index {
+                value: left,
+                index: right,
+                ..
+            }
+            | AST::Equality(left, _, right)
+            | AST::Term(left, _, right, _)
+            | AST::Factor(left, _, right, _) => {
+                left.collect_variables(variables);
+                right.collect_variables(variables);
+            }
+            AST::Return { value, .. } => {
+                if let Some(value) = value {
+                    value.collect_variables(variables);
+                }
+            }
+            AST::Slice {
+                value, start, end, ..
+            } => {
+                value.collect_variables(variables);
+
+                for bound in [start, end].into_iter().flatten() {
+                    bound.collect_variables(variables);
+                }
+            }
+            AST::Unary(_, value, _)
+            | AST::Group(value)
+            | AST::Field { value, .. }
+            | AST::Propagate { value, .. } => value.collect_variables(variables),
+            AST::List { items } => {
+                for item in items {
+                    item.collect_variables(variables);
+                }
+            }
+            AST::Map { entries } => {
+                for (_, value) in entries {
+                    value.collect_variables(variables);
+                }
+            }
+            AST::Identifier(variable) | AST::Import { variable, .. } => variables.push(variable),
+            AST::Comment { .. } | AST::Number(_) | AST::String(_) | AST::None { .. } => (),
+        }
+    }
+}
+
+/// A name that is either defined or used by the script. The binding is filled
+/// in by the resolver, the parser always leaves it empty.
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub token: Token,
+    pub binding: Option<Binding>,
+}
+
+impl Variable {
+    pub fn from_token(token: Token) -> Variable {
+        let name = match &token.token_type {
+            TokenTypes::Identifier { value } => value.to_string(),
+            _ => panic!("Expected identifier, got: {:?}", token),
+        };
+
+        Variable {
+            name,
+            token,
+            binding: None,
+        }
+    }
+}
+
+/// The name of the global that the module imported with `import "path"` is
+/// stored in. It contains a space, so scripts can never refer to it directly.
+pub fn import_name(path: &str) -> String {
+    format!("import {:?}", path)
+}
+
+/// The symbol used for equality eqns. Either == (Equal) or != (NotEqual)
+#[derive(Debug, PartialEq, Clone)]
+pub enum EqualitySymbol {
+    Equal,
+    NotEqual,
+}
+
+impl Display for EqualitySymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EqualitySymbol::Equal => write!(f, "=="),
+            EqualitySymbol::NotEqual => write!(f, "!="),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TermSymbol {
+    Add,
+    Sub,
+}
+
+impl Display for TermSymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TermSymbol::Add => write!(f, "+"),
+            TermSymbol::Sub => write!(f, "-"),
+        }
+    }
+}
+
+/// The symbol used for factor eqns. Either * (Mul) or / (Div)
+#[derive(Debug, PartialEq, Clone)]
+pub enum FactorSymbol {
+    Mul,
+    Div,
+}
+
+impl Display for FactorSymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FactorSymbol::Mul => write!(f, "*"),
+            FactorSymbol::Div => write!(f, "/"),
+        }
+    }
+}
+
+type Tokens = Vec<Token>;
+
+/// A script that cannot be parsed. The parser unwinds with this as the payload
+/// when it can point at the token that is wrong, along with notes on how to
+/// fix it. Like runtime errors, it skips the panic hook.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub token: Token,
+    pub message: String,
+    pub help: Vec<String>,
+}
+
+fn parse_error(token: &Token, message: String, help: Vec<String>) -> ! {
+    std::panic::resume_unwind(Box::new(ParseError {
+        token: token.clone(),
+        message,
+        help,
+    }))
+}
+
+thread_local! {
+    /// The last token of the script being parsed, which errors about a script
+    /// that ends too early are reported against
+    static END: RefCell<Option<Token>> = const { RefCell::new(None) };
+}
+
+/// The source of a token, as it was written
+fn text(token: &Token) -> String {
+    token
+        .line_contents
+        .chars()
+        .skip(token.start)
+        .take(token.end - token.start)
+        .collect()
+}
+
+/// Fails because `token` is not what the parser expected
+fn unexpected(token: &Token, expected: &str, help: Vec<String>) -> ! {
+    parse_error(
+        token,
+        format!("Expected {}, got `{}`", expected, text(token)),
+        help,
+    )
+}
+
+/// Takes the next token, failing if the script has already ended
+fn next(tokens: &mut Tokens, expected: &str) -> Token {
+    match tokens.pop() {
+        Some(token) => token,
+        None => {
+            let end = END
+                .with(|end| end.borrow().clone())
+                .expect("Only scripts with tokens can end too early");
+
+            parse_error(
+                &end,
+                format!("Expected {}, but the script ended", expected),
+                Vec::new(),
+            )
+        }
+    }
+}
+
+/// Takes the next token, failing unless it is a `token_type`
+fn expect(tokens: &mut Tokens, token_type: TokenTypes, expected: &str) -> Token {
+    let token = next(tokens, expected);
+
+    if token.token_type != token_type {
+        unexpected(&token, expected, Vec::new());
+    }
+
+    token
+}
+
+/// Takes the bracket that closes `open`, pointing at `open` if it is missing
+fn close(tokens: &mut Tokens, token_type: TokenTypes, expected: &str, open: &Token) -> Token {
+    close_any(tokens, &[token_type], expected, open)
+}
+
+/// Takes the comma between two items or the bracket that closes them, and
+/// returns whether the items are closed
+fn close_items(tokens: &mut Tokens, token_type: TokenTypes, expected: &str, open: &Token) -> bool {
+    close_any(
+        tokens,
+        &[TokenTypes::Comma, token_type.clone()],
+        expected,
+        open,
+    )
+    .token_type
+        == token_type
+}
+
+/// Takes the next token inside of `open`, which has to be one of `accepted`
+fn close_any(tokens: &mut Tokens, accepted: &[TokenTypes], expected: &str, open: &Token) -> Token {
+    let help = vec![format!(
+        "`{}` on line {} is never closed",
+        text(open),
+        open.line
+    )];
+
+    match tokens.pop() {
+        Some(token) if accepted.contains(&token.token_type) => token,
+        Some(token) => unexpected(&token, expected, help),
+        None => parse_error(
+            open,
+            format!("Expected {}, but the script ended", expected),
+            help,
+        ),
+    }
+}
+
+#[inline]
+pub fn parse(tokens: &mut Tokens, config: &Config) -> AST {
+    END.with(|end| *end.borrow_mut() = tokens.last().cloned());
+
+    // Rust works best with pop and doesn't like you removing the first element
+    // from a vec because it is slow. The solution is to reverse the vec so it
+    // is faster
+    tokens.reverse();
+
+    let block = parse_block(tokens, config);
+
+    // Blocks stop at anything that cannot start a statement, which is only
+    // allowed at the end of a function body
+    if let Some(token) = tokens.last() {
+        unexpected(token, "a statement", Vec::new());
+    }
+
+    block
+}
+
+pub fn parse_block(tokens: &mut Tokens, config: &Config) -> AST {
+    AST::Block {
+        statements: parse_block_internal(tokens, config),
+    }
+}
+
+fn is_valid_body_token(token: &Token) -> bool {
+    matches!(
+        &token.token_type,
+        TokenTypes::Identifier { .. }
+            | TokenTypes::Comment { .. }
+            | TokenTypes::Number { .. }
+            | TokenTypes::String { .. }
+            | TokenTypes::OpenParen
+            | TokenTypes::OpenBracket
+            | TokenTypes::Plus
+            | TokenTypes::Minus
+    )
+}
+
+fn peek_type(tokens: &Tokens) -> Option<TokenTypes> {
+    tokens.last().map(|token| token.token_type.clone())
+}
+
+fn parse_block_internal(tokens: &mut Tokens, config: &Config) -> Vec<AST> {
+    let mut statements = Vec::new();
+
+    while !tokens.is_empty() && is_valid_body_token(&tokens[tokens.len() - 1]) {
+        let is_comment = matches!(
+            &tokens[tokens.len() - 1].token_type,
+            TokenTypes::Comment { .. }
+        );
+
+        statements.push(*parse_statement(tokens, config));
+
+        if !is_comment {
+            match tokens.pop() {
+                Some(token) if token.token_type == TokenTypes::Semi => (),
+                Some(token) => unexpected(
+                    &token,
+                    "`;`",
+                    vec![String::from(
+                        "every statement ends with `;`, is one missing before this?",
+                    )],
+                ),
+                None => {
+                    next(tokens, "`;`");
+                }
+            }
+        }
+    }
+
+    statements
+}
+
+fn parse_statement(tokens: &mut Tokens, config: &Config) -> BAST {
+    let token = next(tokens, "a statement");
+
+    match &token.token_type {
+        TokenTypes::Identifier { value: keyword } => {
+            if config.match_function_keyword(keyword)
+                && peek_type(tokens).is_some_and(|next| next != TokenTypes::OpenParen)
+            {
+                return parse_function_definition(tokens, config, token);
+            }
+
+            if config.match_variable_keyword(keyword)
+                && peek_type(tokens).is_some_and(|next| next.is_identifier())
+            {
+                return parse_variable_definition(tokens, config, token);
+            }
+
+            // `return` is also a valid name, so the text keyword is only a
+            // return statement if it is not being assigned to or called. The
+            // packed character is always a return statement.
+            if config.match_return_keyword(keyword)
+                && (keyword == RETURN_CHARACTER
+                    || !matches!(
+                        peek_type(tokens),
+                        Some(TokenTypes::Equals) | Some(TokenTypes::OpenParen)
+                    ))
+            {
+                return parse_return(tokens, config, token);
+            }
+
+            if peek_type(tokens) == Some(TokenTypes::Equals) {
+                return parse_assignment(tokens, config, token);
+            }
+
+            // A name followed by another value is never a statement, so the
+            // name is most likely a misspelled keyword (e.g. `toiminot f()`)
+            if matches!(
+                peek_type(tokens),
+                Some(
+                    TokenTypes::Identifier { .. }
+                        | TokenTypes::Number { .. }
+                        | TokenTypes::String { .. }
+                )
+            ) {
+                if let Some(help) = suggest_keyword(config, keyword) {
+                    parse_error(&token, format!("Unknown keyword `{}`", keyword), vec![help]);
+                }
+            }
+
+            // Anything else is an expression statement. Function calls are
+            // handled by the expression parser so that their results can be
+            // used by further operators (e.g. `add(1, 2) + 3`)
+            tokens.push(token);
+            let expression = parse_expression(tokens, config);
+
+            if peek_type(tokens) == Some(TokenTypes::Equals) {
+                return parse_index_assignment(tokens, config, expression);
+            }
+
+            expression
+        }
+        TokenTypes::Comment { value } => Box::new(AST::Comment {
+            value: value.to_string(),
+        }),
+        _ => {
+            tokens.push(token);
+            parse_expression(tokens, config)
+        }
+    }
+}
+
+fn parse_function_definition(tokens: &mut Tokens, config: &Config, keyword_token: Token) -> BAST {
+    let name = next(tokens, "a function name");
+    if !name.token_type.is_identifier() {
+        unexpected(&name, "a function name", Vec::new());
+    }
+
+    let params = parse_params(tokens);
+    let body = parse_function_body(tokens, config);
+
+    Box::new(AST::FunctionDefinition {
+        variable: Variable::from_token(name),
+        params,
+        body,
+        keyword_token,
+    })
+}
+
+/// Parses a function without a name, used as a value (e.g.
+/// `let add = function (a, b) { a + b; };`)
+fn parse_anonymous_function(tokens: &mut Tokens, config: &Config, keyword_token: Token) -> BAST {
+    let params = parse_params(tokens);
+    let body = parse_function_body(tokens, config);
+
+    Box::new(AST::Function {
+        params,
+        body,
+        keyword_token,
+    })
+}
+
+/// The function keyword is also a valid name, so `function()` could either be
+/// the start of an anonymous function or a call to a function named `function`.
+/// Anonymous functions always have a body directly after their parameters.
+fn is_anonymous_function(tokens: &Tokens) -> bool {
+    if peek_type(tokens) != Some(TokenTypes::OpenParen) {
+        return false;
+    }
+
+    // The tokens are reversed, so we walk backwards to find the closing paren
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().rev() {
+        match token.token_type {
+            TokenTypes::OpenParen => depth += 1,
+            TokenTypes::CloseParen => depth -= 1,
+            _ => (),
+        }
+
+        if depth == 0 {
+            return index > 0 && tokens[index - 1].token_type == TokenTypes::OpenCurly;
+        }
+    }
+
+    false
+}
+
+fn parse_params(tokens: &mut Tokens) -> Vec<Token> {
+    let mut params: Vec<Token> = Vec::new();
+    let open = expect(tokens, TokenTypes::OpenParen, "`(`");
+
+    let mut token = next(tokens, "a parameter name or `)`");
+
+    while token.token_type != TokenTypes::CloseParen {
+        if !token.token_type.is_identifier() {
+            unexpected(&token, "a parameter name", Vec::new());
+        }
+
+        params.push(token);
+
+        // Parameters are separated by commas
+        if close_items(tokens, TokenTypes::CloseParen, "`,` or `)`", &open) {
+            break;
+        }
+
+        token = next(tokens, "a parameter name");
+    }
+
+    params
+}
+
+fn parse_function_body(tokens: &mut Tokens, config: &Config) -> BAST {
+    let open = expect(tokens, TokenTypes::OpenCurly, "`{`");
+
+    let body = parse_block(tokens, config);
+
+    close(tokens, TokenTypes::CloseCurly, "`}`", &open);
+
+    Box::new(body)
+}
+
+fn parse_variable_definition(tokens: &mut Tokens, config: &Config, keyword_token: Token) -> BAST {
+    let token = next(tokens, "a variable name");
+
+    if !token.token_type.is_identifier() {
+        unexpected(&token, "a variable name", Vec::new());
+    }
+
+    expect(tokens, TokenTypes::Equals, "`=`");
+
+    let value = parse_expression(tokens, config);
+
+    Box::new(AST::VariableDefinition {
+        variable: Variable::from_token(token),
+        value,
+        keyword_token,
+    })
+}
+
+fn parse_function_call(tokens: &mut Tokens, config: &Config, function: BAST) -> BAST {
+    let mut args = Vec::new();
+
+    let paren = expect(tokens, TokenTypes::OpenParen, "`(`");
+
+    let token = match function.as_ref() {
+        AST::Identifier(variable) => variable.token.clone(),
+        _ => paren.clone(),
+    };
+
+    if peek_type(tokens) == Some(TokenTypes::CloseParen) {
+        tokens.pop();
+    } else {
+        loop {
+            args.push(*parse_expression(tokens, config));
+
+            // Arguments are separated by commas
+            if close_items(tokens, TokenTypes::CloseParen, "`,` or `)`", &paren) {
+                break;
+            }
+        }
+    }
+
+    Box::new(AST::FunctionCall {
+        function,
+        args,
+        token,
+    })
+}
+
+fn parse_assignment(tokens: &mut Tokens, config: &Config, name: Token) -> BAST {
+    expect(tokens, TokenTypes::Equals, "`=`");
+
+    let value = parse_expression(tokens, config);
+
+    Box::new(AST::Assignment {
+        variable: Variable::from_token(name),
+        value,
+    })
+}
+
+/// Assigning to anything other than a name has to go through the expression
+/// parser first, because `xs[0]` and `make_list()[0]` both look like
+/// expressions until the `=` is reached
+fn parse_index_assignment(tokens: &mut Tokens, config: &Config, target: BAST) -> BAST {
+    let equals = next(tokens, "`=`");
+
+    if !matches!(*target, AST::Index { .. } | AST::Field { .. }) {
+        parse_error(
+            target.token().unwrap_or(&equals),
+            String::from("Can only assign to variables, list items and map fields"),
+            Vec::new(),
+        );
+    }
+
+    let value = parse_expression(tokens, config);
+
+    Box::new(AST::IndexAssignment { target, value })
+}
+
+fn parse_return(tokens: &mut Tokens, config: &Config, keyword_token: Token) -> BAST {
+    let value = match peek_type(tokens) {
+        Some(TokenTypes::Semi) => None,
+        _ => Some(parse_expression(tokens, config)),
+    };
+
+    Box::new(AST::Return {
+        value,
+        keyword_token,
+    })
+}
+
+#[inline]
+fn parse_expression(tokens: &mut Tokens, config: &Config) -> BAST {
+    equality(tokens, config)
+}
+
+/// Responsible for comparing values. Because it is at the top of the stack, it
+/// has the lowest priority.
+///
+/// Based on the following rule:
+/// ```ebnf
+/// equality ::= term {'==' | '!=' term}
+/// ```
+fn equality(tokens: &mut Tokens, config: &Config) -> BAST {
+    let mut left = term(tokens, config);
+
+    loop {
+        let operator = match peek_type(tokens) {
+            Some(TokenTypes::EqualEqual) => EqualitySymbol::Equal,
+            Some(TokenTypes::BangEqual) => EqualitySymbol::NotEqual,
+            _ => return left,
+        };
+
+        tokens.pop();
+
+        let right = term(tokens, config);
+        left = Box::new(AST::Equality(left, operator, right));
+    }
+}
+
+/// Responsible for parsing basic addition and subtraction. It has a lower
+/// priority than everything other than equality.
+///
+/// Based on the following rule:
+/// ```ebnf
+/// term ::= factor {'+' | '-' factor}
+/// ```
+fn term(tokens: &mut Tokens, config: &Config) -> BAST {
+    // We should execute factor first, as described by the grammar rule
+    //
+    // Note on mut: within rust, you have to explicitly tell the compiler that a
+    // variable can be changed. This is done with the `mut` keyword. Here,
+    // `left` is replaced every time another term is chained on
+    let mut left = factor(tokens, config);
+
+    loop {
+        // If `'+' | '-'` does not match, we should return the value generated
+        // so far
+        let operator = match peek_type(tokens) {
+            Some(TokenTypes::Plus) => TermSymbol::Add,
+            Some(TokenTypes::Minus) => TermSymbol::Sub,
+            _ => return left,
+        };
+
+        let token = tokens.pop().unwrap();
+
+        // Chained terms (e.g. 1-2-3) are evaluated from left to right, so
+        // everything parsed so far becomes the left side of the next term
+        let right = factor(tokens, config);
+
+        // Box puts the variable on the heap. This allows for the contents of
+        // Box (i.e. Expr) to not have a size that is known at compile time.
+        left = Box::new(AST::Term(left, operator, right, token));
+    }
+}
+
+/// Responsible for handling multiplication and division. This function has a
+/// higher priority than terms (addition and subtraction), but a lower priority
+/// than unary (negations) or groups.
+///
+/// Based on the following rules:
+/// ```ebnf
+/// factor ::= unary {'/' | '*' unary}
+/// ```
+fn factor(tokens: &mut Tokens, config: &Config) -> BAST {
+    // Jump down to unary. It has a higher priority, so should be parsed before
+    // the rest of this function
+    let mut left = unary(tokens, config);
+
+    loop {
+        // Just return the expression if it is not multiplication or division.
+        // Either something below this function has already parsed it, or it
+        // will be parsed by the term function above
+        let operator = match peek_type(tokens) {
+            Some(TokenTypes::Star) => FactorSymbol::Mul,
+            Some(TokenTypes::Slash) => FactorSymbol::Div,
+            _ => return left,
+        };
+
+        let token = tokens.pop().unwrap();
+
+        // Like terms, chained factors (e.g. 8/4/2) are evaluated from left to
+        // right
+        let right = unary(tokens, config);
+        left = Box::new(AST::Factor(left, operator, right, token));
+    }
+}
+
+/// Handles negated numbers. This function is also responsible for triggering
+/// `call`, which handles everything with a higher priority
+///
+/// Based on the following rule:
+/// ```ebnf
+/// unary ::= ['+' | '-'] unary | call
+/// ```
+fn unary(tokens: &mut Tokens, config: &Config) -> BAST {
+    // If it doesn't start with a + or a -, we should send it through to the
+    // call parser to get parsed
+    if !matches!(
+        peek_type(tokens),
+        Some(TokenTypes::Plus | TokenTypes::Minus)
+    ) {
+        return call(tokens, config);
+    }
+
+    let token = tokens.pop().unwrap();
+    let symbol = match token.token_type {
+        TokenTypes::Plus => TermSymbol::Add,
+        TokenTypes::Minus => TermSymbol::Sub,
+        // We have already checked for '+' or '-' above, so we do not need to
+        // handle the case here
+        _ => unreachable!(),
+    };
+
+    // Recursion time. Sends it back to handle the call and second unary case.
+    let num = unary(tokens, config);
+
+    // Return all of th necessary values
+    Box::new(AST::Unary(symbol, num, token))
+}
+
+/// Handles function calls, indexing, fields and the `?` operator. Anything can
+/// be called, so that functions returned from other functions can be called
+/// directly (e.g. `make_counter()()`)
+///
+/// Based on the following rule:
+/// ```ebnf
+/// call ::= primary ['(' [expression {',' expression}] ')' | '[' index ']' | '.' identifier | '?']*
+/// ```
+fn call(tokens: &mut Tokens, config: &Config) -> BAST {
+    let mut expression = primary(tokens, config);
+
+    loop {
+        match peek_type(tokens) {
+            Some(TokenTypes::OpenParen) => {
+                expression = parse_function_call(tokens, config, expression);
+            }
+            Some(TokenTypes::OpenBracket) => {
+                expression = parse_index(tokens, config, expression);
+            }
+            Some(TokenTypes::Dot) => {
+                tokens.pop();
+
+                let token = next(tokens, "a field name");
+                let name = match &token.token_type {
+                    TokenTypes::Identifier { value } => value.to_string(),
+                    _ => unexpected(&token, "a field name", Vec::new()),
+                };
+
+                expression = Box::new(AST::Field {
+                    value: expression,
+                    name,
+                    token,
+                });
+            }
+            Some(TokenTypes::Question) => {
+                let token = tokens.pop().unwrap();
+                expression = Box::new(AST::Propagate {
+                    value: expression,
+                    token,
+                });
+            }
+            _ => return expression,
+        }
+    }
+}
+
+/// Handles indexing and slicing, after the value being indexed has been
+/// parsed.
+///
+/// Based on the following rule:
+/// ```ebnf
+/// index ::= expression | [expression] ':' [expression]
+/// ```
+fn parse_index(tokens: &mut Tokens, config: &Config, value: BAST) -> BAST {
+    let open = expect(tokens, TokenTypes::OpenBracket, "`[`");
+
+    let token = bracket_contents(tokens, &open);
+
+    let start = match peek_type(tokens) {
+        Some(TokenTypes::Colon) => None,
+        _ => Some(parse_expression(tokens, config)),
+    };
+
+    let expression = if peek_type(tokens) == Some(TokenTypes::Colon) {
+        tokens.pop();
+
+        let end = match peek_type(tokens) {
+            Some(TokenTypes::CloseBracket) => None,
+            _ => Some(parse_expression(tokens, config)),
+        };
+
+        AST::Slice {
+            value,
+            start,
+            end,
+            token,
+        }
+    } else {
+        AST::Index {
+            value,
+            index: start.unwrap(),
+            token,
+        }
+    };
+
+    close(tokens, TokenTypes::CloseBracket, "`]`", &open);
+
+    Box::new(expression)
+}
+
+/// Creates a token that covers everything up to the next unmatched `]`, so
+/// that errors can point at a whole index expression rather than a part of it
+fn bracket_contents(tokens: &Tokens, open: &Token) -> Token {
+    let first = match tokens.last() {
+        Some(token) if token.token_type != TokenTypes::CloseBracket => token,
+        Some(token) => unexpected(token, "an index", Vec::new()),
+        None => parse_error(
+            open,
+            String::from("Expected an index, but the script ended"),
+            Vec::new(),
+        ),
+    };
+
+    // The tokens are reversed, so we walk backwards to find the closing bracket
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().rev() {
+        match token.token_type {
+            TokenTypes::OpenBracket | TokenTypes::OpenParen => depth += 1,
+            TokenTypes::CloseBracket | TokenTypes::CloseParen if depth > 0 => depth -= 1,
+            TokenTypes::CloseBracket => {
+                let last = &tokens[index + 1];
+
+                if last.line == first.line {
+                    return Token {
+                        end: last.end,
+                        ..first.clone()
+                    };
+                }
+
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    first.clone()
+}
+
+/// Handles the values that everything else is built out of.
+///
+/// Based on the following rule:
+/// ```ebnf
+/// primary ::= group | list | map | function | import | number | string | none | identifier
+/// ```
+fn primary(tokens: &mut Tokens, config: &Config) -> BAST {
+    // Send groups of to a separate functions to be handled.
+    match peek_type(tokens) {
+        Some(TokenTypes::OpenParen) => return group(tokens, config),
+        Some(TokenTypes::OpenBracket) => return list(tokens, config),
+        // Blocks are only ever parsed after the parameters of a function, so a
+        // curly brace anywhere else has to be a map
+        Some(TokenTypes::OpenCurly) => return map(tokens, config),
+        _ => (),
+    }
+
+    let token = next(tokens, "a value");
+
+    match &token.token_type {
+        TokenTypes::Number { value } => Box::new(AST::Number(*value)),
+        TokenTypes::String { value } => Box::new(AST::String(value.to_string())),
+        TokenTypes::Identifier { value } => {
+            if config.match_function_keyword(value) && is_anonymous_function(tokens) {
+                return parse_anonymous_function(tokens, config, token);
+            }
+
+            // The import keyword is only an import if it is followed by a path,
+            // so it is still a valid name
+            if config.match_import_keyword(value) {
+                if let Some(TokenTypes::String { value: path }) = peek_type(tokens) {
+                    let path_token = tokens.pop().unwrap();
+
+                    return Box::new(AST::Import {
+                        variable: Variable {
+                            name: import_name(&path),
+                            token: path_token,
+                            binding: None,
+                        },
+                        path,
+                        keyword_token: token,
+                    });
+                }
+            }
+
+            if config.match_none_keyword(value) {
+                return Box::new(AST::None {
+                    keyword_token: token,
+                });
+            }
+
+            Box::new(AST::Identifier(Variable::from_token(token)))
+        }
+        _ => unexpected(&token, "a value", Vec::new()),
+    }
+}
+
+/// Handles list literals.
+///
+/// Based on the following rule:
+/// ```ebnf
+/// list ::= '[' [expression {',' expression}] ']'
+/// ```
+fn list(tokens: &mut Tokens, config: &Config) -> BAST {
+    let open = expect(tokens, TokenTypes::OpenBracket, "`[`");
+
+    let mut items = Vec::new();
+
+    if peek_type(tokens) == Some(TokenTypes::CloseBracket) {
+        tokens.pop();
+        return Box::new(AST::List { items });
+    }
+
+    loop {
+        items.push(*parse_expression(tokens, config));
+
+        // Items are separated by commas
+        if close_items(tokens, TokenTypes::CloseBracket, "`,` or `]`", &open) {
+            return Box::new(AST::List { items });
+        }
+    }
+}
+
+/// Handles map literals. Keys are always strings.
+///
+/// Based on the following rule:
+/// ```ebnf
+/// map ::= '{' [string ':' expression {',' string ':' expression}] '}'
+/// ```
+fn map(tokens: &mut Tokens, config: &Config) -> BAST {
+    let open = expect(tokens, TokenTypes::OpenCurly, "`{`");
+
+    let mut entries = Vec::new();
+
+    if peek_type(tokens) == Some(TokenTypes::CloseCurly) {
+        tokens.pop();
+        return Box::new(AST::Map { entries });
+    }
+
+    loop {
+        let token = next(tokens, "a string key");
+        let key = match token.token_type {
+            TokenTypes::String { value } => value,
+            _ => unexpected(&token, "a string key", Vec::new()),
+        };
+
+        expect(tokens, TokenTypes::Colon, "`:`");
+
+        entries.push((key, *parse_expression(tokens, config)));
+
+        // Entries are separated by commas
+        if close_items(tokens, TokenTypes::CloseCurly, "`,` or `}`", &open) {
+            return Box::new(AST::Map { entries });
+        }
+    }
+}
+
+/// Handles everything within parenthesizes.
+///
+/// Based on the following rule:
+/// ```ebnf
+/// group ::= '(' expression ')'
+/// ```
+fn group(tokens: &mut Tokens, config: &Config) -> BAST {
+    // Must start with an opening bracket
+    let open = expect(tokens, TokenTypes::OpenParen, "`(`");
+
+    // Jump all of the way back up to the expression function.
+    // See? I told you that the expression function would make the code more
+    // readable latter
+    let expr = parse_expression(tokens, config);
+
+    // Check for closing bracket
+    close(tokens, TokenTypes::CloseParen, "`)`", &open);
+
+    // Return necessary values
+    Box::new(AST::Group(expr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate::Tokenizer;
+
+    /// Parses a single expression and writes it out with every operation in
+    /// brackets, e.g. `((10 - 3) - 2)`
+    fn grouping(source: &str) -> String {
+        let mut tokens = Tokenizer::from_string(format!("{};", source)).tokenize();
+
+        match parse(&mut tokens, &Config::default()) {
+            AST::Block { statements } => brackets(&statements[0]),
+            _ => unreachable!(),
+        }
+    }
+
+    fn brackets(ast: &AST) -> String {
+        match ast {
+            AST::Equality(left, operator, right) => {
+                format!("({} {} {})", brackets(left), operator, brackets(right))
+            }
+            AST::Term(left, operator, right, _) => {
+                format!("({} {} {})", brackets(left), operator, brackets(right))
+            }
+            AST::Factor(left, operator, right, _) => {
+                format!("({} {} {})", brackets(left), operator, brackets(right))
+            }
+            AST::Unary(operator, value, _) => format!("{}{}", operator, brackets(value)),
+            AST::Group(value) => brackets(value),
+            AST::Number(number) => number.to_string(),
+            ast => panic!("Unexpected node {:?}", ast),
+        }
+    }
+
+    #[test]
+    fn terms_are_left_associative() {
+        assert_eq!(grouping("10 - 3 - 2"), "((10 - 3) - 2)");
+        assert_eq!(grouping("1 + 2 - 3 + 4"), "(((1 + 2) - 3) + 4)");
+    }
+
+    #[test]
+    fn factors_are_left_associative() {
+        assert_eq!(grouping("8 / 4 / 2"), "((8 / 4) / 2)");
+        assert_eq!(grouping("2 * 6 / 3 * 4"), "(((2 * 6) / 3) * 4)");
+    }
+
+    #[test]
+    fn equality_is_left_associative() {
+        assert_eq!(grouping("1 == 1 != 2"), "((1 == 1) != 2)");
+    }
+
+    #[test]
+    fn factors_bind_tighter_than_terms() {
+        assert_eq!(grouping("1 - 2 * 3 - 4 / 2"), "((1 - (2 * 3)) - (4 / 2))");
+        assert_eq!(grouping("(1 - 2) * -3"), "((1 - 2) * -3)");
+    }
+
+    /// Parses a script that has a mistake in it, returning the error
+    fn parse_error_in(source: &str) -> ParseError {
+        let mut tokens = Tokenizer::from_string(source.to_string()).tokenize();
+
+        match std::panic::catch_unwind(move || parse(&mut tokens, &Config::default())) {
+            Ok(ast) => panic!("Expected a parse error, got {:?}", ast),
+            Err(payload) => *payload
+                .downcast::<ParseError>()
+                .expect("Expected the parser to fail with a ParseError"),
+        }
+    }
+
+    #[test]
+    fn unclosed_parens_point_at_the_paren() {
+        let error = parse_error_in("let a = (1 + 2;");
+        assert_eq!(error.message, "Expected `)`, got `;`");
+        assert_eq!(error.help, ["`(` on line 1 is never closed"]);
+
+        let error = parse_error_in("print(1, 2;\nprint(3);");
+        assert_eq!(error.message, "Expected `,` or `)`, got `;`");
+        assert_eq!(error.help, ["`(` on line 1 is never closed"]);
+    }
+
+    #[test]
+    fn scripts_that_end_inside_of_brackets_point_at_the_bracket() {
+        let error = parse_error_in("function f() {\n    print(1);\n");
+        assert_eq!(error.message, "Expected `}`, but the script ended");
+        assert_eq!(
+            (error.token.line, error.token.token_type),
+            (1, TokenTypes::OpenCurly)
+        );
+    }
+
+    #[test]
+    fn missing_semicolons_are_reported_at_the_next_statement() {
+        let error = parse_error_in("let a = 1\nprint(a);");
+        assert_eq!(error.message, "Expected `;`, got `print`");
+        assert_eq!(error.token.line, 2);
+        assert_eq!(
+            error.help,
+            ["every statement ends with `;`, is one missing before this?"]
+        );
+
+        assert_eq!(
+            parse_error_in("print(1)").message,
+            "Expected `;`, but the script ended"
+        );
+    }
+
+    #[test]
+    fn unexpected_tokens_are_reported() {
+        assert_eq!(
+            parse_error_in("let a = ;").message,
+            "Expected a value, got `;`"
+        );
+        assert_eq!(parse_error_in("let a 1;").message, "Expected `=`, got `1`");
+        assert_eq!(
+            parse_error_in("print(1);\n}").message,
+            "Expected a statement, got `}`"
+        );
+        assert_eq!(
+            parse_error_in("let m = {1: 2};").message,
+            "Expected a string key, got `1`"
+        );
+        assert_eq!(
+            parse_error_in("f() = 3;").message,
+            "Can only assign to variables, list items and map fields"
+        );
+    }
+}
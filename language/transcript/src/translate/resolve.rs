@@ -0,0 +1,332 @@
+use super::{Binding, Token, TokenTypes, Variable, AST};
+
+/// A problem found while resolving, reported against the token that caused it
+#[derive(Debug)]
+pub struct ResolveError {
+    pub token: Token,
+    pub message: String,
+}
+
+/// Binds every variable in the AST to the environment slot it will live in at
+/// runtime. `scopes` are the names that are already defined in each scope the
+/// script runs in, outermost first and in slot order. The last one is the
+/// script's own scope, and the ones around it (e.g. the builtins) can be
+/// shadowed by the script. Names already in the script's own scope can be
+/// defined once more, so a script run again in the same globals (e.g. by
+/// `Interpreter::eval_str`) can redefine what the last run defined.
+///
+/// Every scope is checked in full, so all undefined names and duplicate
+/// definitions are returned at once rather than one at a time.
+pub fn resolve(ast: &mut AST, scopes: &[Vec<String>]) -> Result<(), Vec<ResolveError>> {
+    let mut scopes = scopes.to_vec();
+    if scopes.is_empty() {
+        scopes.push(Vec::new());
+    }
+
+    let mut resolver = Resolver {
+        global: scopes.len(),
+        redefinable: scopes.last().map(Vec::len).unwrap_or_default(),
+        redefined: Vec::new(),
+        scopes,
+        functions: 0,
+        errors: Vec::new(),
+    };
+
+    resolver.block(ast);
+
+    if resolver.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(resolver.errors)
+    }
+}
+
+struct Resolver {
+    /// The names defined in each scope, indexed by slot. The first scopes are
+    /// the ones that the script runs in.
+    scopes: Vec<Vec<String>>,
+    /// The number of scopes at the top level of the script
+    global: usize,
+    /// The number of names in the script's scope that were defined before it
+    /// ran, which the script can define again
+    redefinable: usize,
+    /// The slots of those names that the script has defined again
+    redefined: Vec<usize>,
+    /// The number of functions that are being resolved, used to check that
+    /// return statements are inside of one
+    functions: usize,
+    errors: Vec<ResolveError>,
+}
+
+impl Resolver {
+    fn error(&mut self, token: &Token, message: String) {
+        self.errors.push(ResolveError {
+            token: token.clone(),
+            message,
+        });
+    }
+
+    /// Adds a variable to the innermost scope and returns its slot
+    fn declare(&mut self, variable: &Variable) -> usize {
+        let global = self.scopes.len() == self.global;
+        let scope = self.scopes.last_mut().unwrap();
+
+        if let Some(slot) = scope.iter().position(|name| name == &variable.name) {
+            if global && slot < self.redefinable && !self.redefined.contains(&slot) {
+                self.redefined.push(slot);
+                return slot;
+            }
+
+            self.error(
+                &variable.token,
+                format!("'{}' is already defined in this scope", variable.name),
+            );
+            return slot;
+        }
+
+        scope.push(variable.name.to_string());
+        scope.len() - 1
+    }
+
+    fn lookup(&mut self, variable: &mut Variable) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(slot) = scope.iter().position(|name| name == &variable.name) {
+                variable.binding = Some(Binding { depth, slot });
+                return;
+            }
+        }
+
+        self.error(
+            &variable.token,
+            format!("Undefined variable: {}", variable.name),
+        );
+    }
+
+    /// Resolves the statements of a block in the current scope. Function
+    /// definitions are declared before anything else, so functions can call
+    /// functions that are defined further down the file.
+    fn block(&mut self, ast: &mut AST) {
+        let statements = match ast {
+            AST::Block { statements } => statements,
+            _ => return self.node(ast),
+        };
+
+        for statement in statements.iter_mut() {
+            if let AST::FunctionDefinition { variable, .. } = statement {
+                let slot = self.declare(variable);
+                variable.binding = Some(Binding { depth: 0, slot });
+            }
+        }
+
+        for statement in statements.iter_mut() {
+            self.node(statement);
+        }
+    }
+
+    fn function(&mut self, params: &[Token], body: &mut AST) {
+        let mut scope: Vec<String> = Vec::new();
+
+        for param in params {
+            let name = match &param.token_type {
+                TokenTypes::Identifier { value } => value.to_string(),
+                _ => panic!("Function parameters must be identifiers!"),
+            };
+
+            if scope.contains(&name) {
+                self.error(param, format!("Duplicate parameter: {}", name));
+            }
+
+            scope.push(name);
+        }
+
+        self.scopes.push(scope);
+        self.functions += 1;
+        self.block(body);
+        self.functions -= 1;
+        self.scopes.pop();
+    }
+
+    fn node(&mut self, ast: &mut AST) {
+        match ast {
+            AST::Block { .. } => self.block(ast),
+            AST::VariableDefinition {
+                variable, value, ..
+            } => {
+                // The value is resolved first so that `let x = x` refers to an
+                // `x` in an enclosing scope
+                self.node(value);
+
+                let slot = self.declare(variable);
+                variable.binding = Some(Binding { depth: 0, slot });
+            }
+            AST::FunctionDefinition { params, body, .. } | AST::Function { params, body, .. } => {
+                self.function(params, body)
+            }
+            AST::FunctionCall { function, args, .. } => {
+                self.node(function);
+
+                for arg in args {
+                    self.node(arg);
+                }
+            }
+            AST::Assignment { variable, value } => {
+                self.node(value);
+                self.lookup(variable);
+            }
+            AST::Return {
+                value,
+                keyword_token,
+            } => {
+                if self.functions == 0 {
+                    self.error(
+                        keyword_token,
+                        String::from("Cannot return from outside of a function"),
+                    );
+                }
+
+                if let Some(value) = value {
+                    self.node(value);
+                }
+            }
+            AST::IndexAssignment { target, value } => {
+                self.node(target);
+                self.node(value);
+            }
+            AST::List { items } => {
+                for item in items {
+                    self.node(item);
+                }
+            }
+            AST::Map { entries } => {
+                for (_, value) in entries {
+                    self.node(value);
+                }
+            }
+            AST::Field { value, .. } => self.node(value),
+            AST::Index { value, index, .. } => {
+                self.node(value);
+                self.node(index);
+            }
+            AST::Slice {
+                value, start, end, ..
+            } => {
+                self.node(value);
+
+                for bound in [start, end].into_iter().flatten() {
+                    self.node(bound);
+                }
+            }
+            AST::Propagate { value, token } => {
+                if self.functions == 0 {
+                    self.error(token, String::from("Cannot use '?' outside of a function"));
+                }
+
+                self.node(value);
+            }
+            AST::Equality(left, _, right) => {
+                self.node(left);
+                self.node(right);
+            }
+            AST::Term(left, _, right, _) | AST::Factor(left, _, right, _) => {
+                self.node(left);
+                self.node(right);
+            }
+            AST::Unary(_, value, _) | AST::Group(value) => self.node(value),
+            AST::Identifier(variable) | AST::Import { variable, .. } => self.lookup(variable),
+            AST::Comment { .. } | AST::Number(_) | AST::String(_) | AST::None { .. } => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        translate::{parse, Tokenizer},
+        utils::Config,
+    };
+
+    fn resolve_source(source: &str, scopes: &[Vec<String>]) -> Result<(), Vec<ResolveError>> {
+        let mut tokens = Tokenizer::from_string(source.to_string()).tokenize();
+        let mut ast = parse(&mut tokens, &Config::default());
+
+        resolve(&mut ast, scopes)
+    }
+
+    fn builtins() -> Vec<String> {
+        ["print", "values", "len", "map"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    fn messages(result: Result<(), Vec<ResolveError>>) -> Vec<String> {
+        result
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.message)
+            .collect()
+    }
+
+    #[test]
+    fn top_level_definitions_shadow_builtins() {
+        let source = "let values = [1, 2];\nfunction len(list) { return 0; };\nprint(len(values));";
+
+        assert!(resolve_source(source, &[builtins(), Vec::new()]).is_ok());
+    }
+
+    #[test]
+    fn locals_shadow_builtins() {
+        let source = "function f(values) { let map = 1; return map; };\nprint(f(2));";
+
+        assert!(resolve_source(source, &[builtins(), Vec::new()]).is_ok());
+    }
+
+    #[test]
+    fn builtins_are_found_in_the_enclosing_scope() {
+        let mut tokens = Tokenizer::from_string(String::from("len;")).tokenize();
+        let mut ast = parse(&mut tokens, &Config::default());
+
+        resolve(&mut ast, &[builtins(), Vec::new()]).unwrap();
+
+        match &ast {
+            AST::Block { statements } => match &statements[0] {
+                AST::Identifier(variable) => {
+                    assert_eq!(variable.binding, Some(Binding { depth: 1, slot: 2 }))
+                }
+                statement => panic!("Expected an identifier, got {:?}", statement),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn names_cannot_be_defined_twice_in_one_script() {
+        assert_eq!(
+            messages(resolve_source(
+                "let x = 1;\nlet x = 2;",
+                &[builtins(), Vec::new()]
+            )),
+            ["'x' is already defined in this scope"]
+        );
+    }
+
+    #[test]
+    fn globals_from_an_earlier_run_can_be_defined_again_once() {
+        let scopes = [builtins(), vec![String::from("x")]];
+
+        assert!(resolve_source("let x = 2;", &scopes).is_ok());
+        assert_eq!(
+            messages(resolve_source("let x = 2;\nlet x = 3;", &scopes)),
+            ["'x' is already defined in this scope"]
+        );
+    }
+
+    #[test]
+    fn undefined_names_are_still_reported() {
+        assert_eq!(
+            messages(resolve_source("print(missing);", &[builtins(), Vec::new()])),
+            ["Undefined variable: missing"]
+        );
+    }
+}
@@ -1,3 +1,7 @@
+use std::{path::Path, sync::Arc};
+
+use log::debug;
+
 use super::{Token, TokenTypes};
 
 pub struct Tokenizer {
@@ -8,6 +12,7 @@ pub struct Tokenizer {
 
     token_start: usize,
     output: Vec<Token>,
+    path: Option<Arc<Path>>,
 }
 
 impl Tokenizer {
@@ -20,6 +25,16 @@ impl Tokenizer {
 
             current_line: 1,
             line_start: 0,
+            path: None,
+        }
+    }
+
+    /// Tokenizes the contents of a file, marking every token with the path
+    /// that it came from
+    pub fn from_file(path: &Path, input: String) -> Self {
+        Tokenizer {
+            path: Some(Arc::from(path)),
+            ..Tokenizer::from_string(input)
         }
     }
 }
@@ -35,6 +50,8 @@ impl Tokenizer {
     }
 
     fn scan_token(&mut self) {
+        // Skipped whitespace should not become a part of the next token
+        self.token_start = self.current_char;
         let current = self.advance();
 
         if current.is_none() {
@@ -45,9 +62,21 @@ impl Tokenizer {
             '+' => self.add_token(TokenTypes::Plus),
             '-' => self.add_token(TokenTypes::Minus),
             '*' => self.add_token(TokenTypes::Star),
-            '=' => self.add_token(TokenTypes::Equals),
+            '=' => {
+                if self.peek() == Some('=') {
+                    self.advance();
+                    self.add_token(TokenTypes::EqualEqual);
+                } else {
+                    self.add_token(TokenTypes::Equals);
+                }
+            }
+            '!' if self.peek() == Some('=') => {
+                self.advance();
+                self.add_token(TokenTypes::BangEqual);
+            }
+            '?' => self.add_token(TokenTypes::Question),
             '/' => {
-                if self.peek().unwrap() == '/' {
+                if self.peek() == Some('/') {
                     self.advance();
                     let comment_content = self.scan_comment().trim().to_string();
                     self.add_token(TokenTypes::Comment {
@@ -62,9 +91,13 @@ impl Tokenizer {
             ')' => self.add_token(TokenTypes::CloseParen),
             '{' => self.add_token(TokenTypes::OpenCurly),
             '}' => self.add_token(TokenTypes::CloseCurly),
+            '[' => self.add_token(TokenTypes::OpenBracket),
+            ']' => self.add_token(TokenTypes::CloseBracket),
 
             ';' => self.add_token(TokenTypes::Semi),
             ',' => self.add_token(TokenTypes::Comma),
+            ':' => self.add_token(TokenTypes::Colon),
+            '.' => self.add_token(TokenTypes::Dot),
 
             // Ignore whitespace
             ' ' | '\r' | '\t' => (),
@@ -77,7 +110,7 @@ impl Tokenizer {
             }
 
             '"' => self.scan_string(),
-            '0'..='9' => self.scan_number(),
+            current @ '0'..='9' => self.scan_number(current),
             current => self.scan_identifier(current),
         }
     }
@@ -92,7 +125,7 @@ impl Tokenizer {
 
     fn add_token(&mut self, token_type: TokenTypes) {
         if self.line_start > self.token_start {
-            println!("Tokenizer: Token spanned across two lines");
+            debug!("Tokenizer: Token spanned across two lines");
         }
 
         self.output.push(Token {
@@ -106,6 +139,7 @@ impl Tokenizer {
                 .nth(self.current_line - 1)
                 .unwrap()
                 .to_string(),
+            path: self.path.clone(),
         });
 
         self.token_start = self.current_char;
@@ -118,7 +152,7 @@ impl Tokenizer {
     fn scan_comment(&mut self) -> String {
         let mut comment = String::new();
 
-        while self.peek().unwrap() != '\n' {
+        while self.peek().is_some_and(|next| next != '\n') {
             comment.push(self.advance().unwrap());
         }
 
@@ -139,15 +173,18 @@ impl Tokenizer {
         self.add_token(TokenTypes::String { value: string });
     }
 
-    fn scan_number(&mut self) {
+    fn scan_number(&mut self, first: char) {
         let mut current = self.advance();
         let mut number = String::new();
+        number.push(first);
 
-        while current.is_some() && current.unwrap().is_digit(10) {
+        while current.is_some() && current.unwrap().is_ascii_digit() {
             number.push(current.unwrap());
             current = self.advance();
         }
 
+        self.current_char -= 1;
+
         self.add_token(TokenTypes::Number {
             value: number.parse().unwrap(),
         });
@@ -158,7 +195,7 @@ impl Tokenizer {
         let mut identifier = String::new();
         identifier.push(first);
 
-        while current.is_some() && current.unwrap().is_alphanumeric() {
+        while current.is_some() && (current.unwrap().is_alphanumeric() || current.unwrap() == '_') {
             identifier.push(current.unwrap());
             current = self.advance();
         }
@@ -0,0 +1,432 @@
+use std::{
+    env,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tini::Ini;
+
+use crate::{
+    file::{
+        BUILTIN_CHARACTERS, FUNCTION_DEFINITION_CHARACTER, IMPORT_CHARACTER, NONE_CHARACTER,
+        RETURN_CHARACTER, VARIABLE_DEFINITION_CHARACTER,
+    },
+    Error,
+};
+
+use super::Locale;
+
+/// The name of config files, both in projects and in the user's config
+/// directory
+pub const CONFIG_FILE: &str = "transcript.ini";
+
+pub(super) const DEFAULT_FUNCTION_KEYWORD: &str = "function";
+pub(super) const DEFAULT_VARIABLE_KEYWORD: &str = "let";
+pub(super) const DEFAULT_RETURN_KEYWORD: &str = "return";
+pub(super) const DEFAULT_NONE_KEYWORD: &str = "none";
+pub(super) const DEFAULT_IMPORT_KEYWORD: &str = "import";
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub function_keyword: String,
+    pub variable_keyword: String,
+    pub return_keyword: String,
+    pub none_keyword: String,
+    pub import_keyword: String,
+    /// Directories that are searched for imported scripts, after the
+    /// directory of the script doing the importing
+    pub search_path: Vec<String>,
+    /// Localized names for builtin functions, as pairs of the english name and
+    /// the localized one
+    pub builtin_names: Vec<(String, String)>,
+    /// The file that translations of the names used by a project are read
+    /// from, see `Glossary`
+    pub glossary: Option<String>,
+    /// The section of the glossary to translate names with
+    pub locale: Option<String>,
+    /// The name of the language that the keywords are in, e.g. "Finnish"
+    pub language: Option<String>,
+    /// Directories that locale packs are read from, see `Locale`
+    pub locale_paths: Vec<String>,
+    /// The locale packs that were found in `locale_paths`
+    pub locales: Vec<Locale>,
+    /// The config files that were read, from the lowest precedence to the
+    /// highest
+    pub files: Vec<PathBuf>,
+    /// Where each setting was last set, for reporting problems with it
+    origins: Vec<Origin>,
+}
+
+#[derive(Clone, Debug)]
+struct Origin {
+    section: String,
+    key: String,
+    file: PathBuf,
+    line: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            function_keyword: DEFAULT_FUNCTION_KEYWORD.to_string(),
+            variable_keyword: DEFAULT_VARIABLE_KEYWORD.to_string(),
+            return_keyword: DEFAULT_RETURN_KEYWORD.to_string(),
+            none_keyword: DEFAULT_NONE_KEYWORD.to_string(),
+            import_keyword: DEFAULT_IMPORT_KEYWORD.to_string(),
+            search_path: Vec::new(),
+            builtin_names: Vec::new(),
+            glossary: None,
+            locale: None,
+            language: None,
+            locale_paths: Vec::new(),
+            locales: Vec::new(),
+            files: Vec::new(),
+            origins: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Finds and reads the config for a script in `directory`. Each config
+    /// file that is found overrides the ones before it, in this order:
+    ///
+    /// 1. `transcript/transcript.ini` in the user's config directory
+    ///    (`$XDG_CONFIG_HOME`, or `~/.config` if that is not set)
+    /// 2. The nearest `transcript.ini` in `directory` or one of its parents
+    /// 3. `explicit`, if it is given
+    ///
+    /// Anything that no file sets keeps its default, so scripts can be run
+    /// without any config file at all. Fails with every problem found by
+    /// `validate`.
+    ///
+    /// Locale packs are read from `transcript/locales` in the user's config
+    /// directory, followed by the directories that the config files list.
+    pub fn discover(directory: &Path, explicit: Option<&Path>) -> Result<Config, Error> {
+        let mut config = Config::default();
+
+        if let Some(user) = user_config() {
+            let locales = user.join("locales");
+            config
+                .locale_paths
+                .push(locales.to_string_lossy().to_string());
+
+            let file = user.join(CONFIG_FILE);
+            if file.is_file() {
+                config.read(&file)?;
+            }
+        }
+
+        let directory = directory
+            .canonicalize()
+            .unwrap_or_else(|_| directory.to_path_buf());
+
+        if let Some(file) = directory
+            .ancestors()
+            .map(|directory| directory.join(CONFIG_FILE))
+            .find(|file| file.is_file())
+        {
+            config.read(&file)?;
+        }
+
+        if let Some(file) = explicit {
+            config.read(file)?;
+        }
+
+        config.locales = Locale::installed(&config.locale_paths)?;
+
+        config.validated()
+    }
+
+    /// Reads a single config file on top of the defaults
+    pub fn load(file: &Path) -> Result<Config, Error> {
+        let mut config = Config::default();
+        config.read(file)?;
+
+        config.validated()
+    }
+
+    fn validated(self) -> Result<Config, Error> {
+        match self.validate() {
+            problems if problems.is_empty() => Ok(self),
+            problems => Err(Error::InvalidConfig(problems)),
+        }
+    }
+
+    /// The file and line that last set `key` in `section`
+    pub(super) fn origin(&self, section: &str, key: &str) -> Option<(PathBuf, usize)> {
+        self.origins
+            .iter()
+            .rev()
+            .find(|origin| origin.section == section && origin.key == key)
+            .map(|origin| (origin.file.clone(), origin.line))
+    }
+
+    /// Overrides whatever `file` sets. Paths in the file are relative to the
+    /// directory that it is in.
+    fn read(&mut self, file: &Path) -> Result<(), Error> {
+        let error = |error: &dyn Display| Error::Config(format!("{}: {}", file.display(), error));
+        let contents = fs::read_to_string(file).map_err(|e| error(&e))?;
+        let config_file = Ini::from_string(contents.as_str()).map_err(|e| error(&e))?;
+        let directory = file.parent().unwrap_or(Path::new(""));
+        let relative = |path: &str| directory.join(path).to_string_lossy().to_string();
+
+        if let Some(function) = config_file.get::<String>("keywords", "function") {
+            self.function_keyword = function;
+        }
+
+        if let Some(variable) = config_file.get::<String>("keywords", "variable") {
+            self.variable_keyword = variable;
+        }
+
+        if let Some(return_keyword) = config_file.get::<String>("keywords", "return") {
+            self.return_keyword = return_keyword;
+        }
+
+        if let Some(none) = config_file.get::<String>("keywords", "none") {
+            self.none_keyword = none;
+        }
+
+        if let Some(import) = config_file.get::<String>("keywords", "import") {
+            self.import_keyword = import;
+        }
+
+        if let Some(search_path) = config_file.get_vec::<String>("modules", "search_path") {
+            self.search_path = search_path.iter().map(|path| relative(path)).collect();
+        }
+
+        if let Some(glossary) = config_file.get::<String>("glossary", "path") {
+            self.glossary = Some(relative(&glossary));
+        }
+
+        if let Some(locale) = config_file.get::<String>("glossary", "locale") {
+            self.locale = Some(locale);
+        }
+
+        if let Some(language) = config_file.get::<String>("locale", "language") {
+            self.language = Some(language);
+        }
+
+        if let Some(locale_paths) = config_file.get_vec::<String>("locales", "path") {
+            self.locale_paths
+                .extend(locale_paths.iter().map(|path| relative(path)));
+        }
+
+        // Builtins are merged one by one, so a project can rename a builtin
+        // without repeating every name from the user's config
+        for (name, localized) in config_file.section_iter("builtins") {
+            self.builtin_names.retain(|(builtin, _)| builtin != name);
+            self.builtin_names
+                .push((name.to_string(), localized.to_string()));
+        }
+
+        self.record_origins(file, &contents);
+        self.files.push(file.to_path_buf());
+
+        Ok(())
+    }
+
+    /// Remembers the line of every setting in a config file. Later files come
+    /// last, so they are found first when looking an origin up.
+    fn record_origins(&mut self, file: &Path, contents: &str) {
+        let mut section = "";
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section = name.trim();
+            } else if let Some((key, _)) = line.split_once('=') {
+                self.origins.push(Origin {
+                    section: section.to_string(),
+                    key: key.trim().to_string(),
+                    file: file.to_path_buf(),
+                    line: index + 1,
+                });
+            }
+        }
+    }
+}
+
+/// The directory holding the config for a user, which applies to every script
+/// they run
+fn user_config() -> Option<PathBuf> {
+    let directory = match env::var_os("XDG_CONFIG_HOME") {
+        Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(directory.join("transcript"))
+}
+
+impl Config {
+    pub fn match_function_keyword(&self, keyword: &str) -> bool {
+        if keyword == FUNCTION_DEFINITION_CHARACTER {
+            return true;
+        }
+
+        if keyword == DEFAULT_FUNCTION_KEYWORD {
+            return true;
+        }
+
+        keyword == self.function_keyword
+    }
+
+    pub fn match_variable_keyword(&self, keyword: &str) -> bool {
+        if keyword == VARIABLE_DEFINITION_CHARACTER {
+            return true;
+        }
+
+        if keyword == DEFAULT_VARIABLE_KEYWORD {
+            return true;
+        }
+
+        keyword == self.variable_keyword
+    }
+
+    pub fn match_return_keyword(&self, keyword: &str) -> bool {
+        if keyword == RETURN_CHARACTER {
+            return true;
+        }
+
+        if keyword == DEFAULT_RETURN_KEYWORD {
+            return true;
+        }
+
+        keyword == self.return_keyword
+    }
+
+    pub fn match_none_keyword(&self, keyword: &str) -> bool {
+        if keyword == NONE_CHARACTER {
+            return true;
+        }
+
+        if keyword == DEFAULT_NONE_KEYWORD {
+            return true;
+        }
+
+        keyword == self.none_keyword
+    }
+
+    /// Returns the packed character of the builtin called `name`, which can
+    /// be its english name, its localized name or the character itself
+    pub fn match_builtin(&self, name: &str) -> Option<&'static str> {
+        let english = self
+            .builtin_names
+            .iter()
+            .find(|(_, localized)| localized == name)
+            .map_or(name, |(english, _)| english.as_str());
+
+        BUILTIN_CHARACTERS
+            .iter()
+            .find(|(builtin, character)| *builtin == english || *character == name)
+            .map(|(_, character)| *character)
+    }
+
+    /// The name that a builtin is shown as when it is unpacked
+    pub fn builtin_name<'a>(&'a self, english: &'a str) -> &'a str {
+        self.builtin_names
+            .iter()
+            .find(|(builtin, _)| builtin == english)
+            .map_or(english, |(_, localized)| localized.as_str())
+    }
+
+    pub fn match_import_keyword(&self, keyword: &str) -> bool {
+        if keyword == IMPORT_CHARACTER {
+            return true;
+        }
+
+        if keyword == DEFAULT_IMPORT_KEYWORD {
+            return true;
+        }
+
+        keyword == self.import_keyword
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes config files under a directory of their own, returning the
+    /// directory
+    fn configs(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = env::temp_dir().canonicalize().unwrap().join(format!(
+            "transcript-{}-{}",
+            test,
+            std::process::id()
+        ));
+
+        for (name, contents) in files {
+            let file = directory.join(name);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, contents).unwrap();
+        }
+
+        directory
+    }
+
+    // Everything that depends on `XDG_CONFIG_HOME` is in this one test, as
+    // tests run in parallel in the same process
+    #[test]
+    fn later_config_files_override_earlier_ones() {
+        let directory = configs(
+            "discover",
+            &[
+                (
+                    "user/transcript/transcript.ini",
+                    "[keywords]\nfunction = funktio\nvariable = olkoon\nreturn = palauta\n\n[builtins]\nprint = tulosta\n",
+                ),
+                (
+                    "project/transcript.ini",
+                    "[keywords]\nvariable = muuttuja\nreturn = anna\n",
+                ),
+                ("project/scripts/main.script", ""),
+                ("explicit.ini", "[keywords]\n\nreturn = palautus\n"),
+            ],
+        );
+        env::set_var("XDG_CONFIG_HOME", directory.join("user"));
+
+        let user = directory.join("user/transcript").join(CONFIG_FILE);
+        let project = directory.join("project").join(CONFIG_FILE);
+        let explicit = directory.join("explicit.ini");
+
+        let config = Config::discover(&directory.join("project/scripts"), None).unwrap();
+
+        assert_eq!(config.files, vec![user.clone(), project.clone()]);
+        assert_eq!(config.function_keyword, "funktio");
+        assert_eq!(config.variable_keyword, "muuttuja");
+        assert_eq!(config.return_keyword, "anna");
+        assert_eq!(config.builtin_name("print"), "tulosta");
+        assert_eq!(
+            config.origin("keywords", "function"),
+            Some((user.clone(), 2))
+        );
+        assert_eq!(
+            config.origin("keywords", "variable"),
+            Some((project.clone(), 2))
+        );
+        assert_eq!(
+            config.origin("keywords", "return"),
+            Some((project.clone(), 3))
+        );
+        assert_eq!(config.origin("builtins", "print"), Some((user.clone(), 7)));
+
+        let config = Config::discover(&directory.join("project/scripts"), Some(&explicit)).unwrap();
+
+        assert_eq!(
+            config.files,
+            vec![user.clone(), project.clone(), explicit.clone()]
+        );
+        assert_eq!(config.variable_keyword, "muuttuja");
+        assert_eq!(config.return_keyword, "palautus");
+        assert_eq!(config.origin("keywords", "variable"), Some((project, 2)));
+        assert_eq!(config.origin("keywords", "return"), Some((explicit, 3)));
+
+        env::remove_var("XDG_CONFIG_HOME");
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+use std::{fs, path::Path};
+
+use crate::{
+    file::BUILTIN_CHARACTERS,
+    translate::{Token, TokenTypes},
+    Error,
+};
+
+use super::Config;
+
+/// A language that scripts can be written in, read from a locale pack. Locale
+/// packs are config files with the keywords and builtin names of one language,
+/// named after the language code (e.g. `fi.ini`), in one of the directories
+/// listed under `[locales]`:
+///
+/// ```ini
+/// [locales]
+/// path = locales
+/// ```
+#[derive(Clone, Debug)]
+pub struct Locale {
+    /// The language code, taken from the name of the file
+    pub code: String,
+    /// The keywords and builtin names of the language
+    pub config: Config,
+}
+
+impl Locale {
+    /// English is always available, since the english keywords work in every
+    /// locale
+    pub fn english() -> Locale {
+        let mut config = Config::default();
+        config.language = Some(String::from("English"));
+
+        Locale {
+            code: String::from("en"),
+            config,
+        }
+    }
+
+    /// The name of the language, or its code if the locale pack does not name it
+    pub fn language(&self) -> &str {
+        self.config.language.as_deref().unwrap_or(&self.code)
+    }
+
+    /// Finds an installed locale by its code
+    pub fn find(code: &str, config: &Config) -> Result<Locale, Error> {
+        let mut locales = [Locale::english()]
+            .into_iter()
+            .chain(config.locales.iter().cloned());
+
+        match locales.find(|locale| locale.code == code) {
+            Some(locale) => Ok(locale),
+            None => Err(Error::Config(format!(
+                "There is no locale called '{}', the installed locales are: en{}",
+                code,
+                config
+                    .locales
+                    .iter()
+                    .map(|locale| format!(", {}", locale.code))
+                    .collect::<String>()
+            ))),
+        }
+    }
+
+    /// Reads every locale pack in `directories`. Packs in later directories
+    /// replace packs with the same code in earlier ones.
+    pub fn installed(directories: &[String]) -> Result<Vec<Locale>, Error> {
+        let mut locales: Vec<Locale> = Vec::new();
+
+        for directory in directories {
+            let entries = match fs::read_dir(directory) {
+                Ok(entries) => entries,
+                // Missing directories are skipped, so a shared config can list
+                // directories that only some people have
+                Err(_) => continue,
+            };
+
+            let mut files: Vec<_> = entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.extension().is_some_and(|extension| extension == "ini"))
+                .collect();
+            files.sort();
+
+            for file in files {
+                let locale = Locale::load(&file)?;
+                locales.retain(|other| other.code != locale.code);
+                locales.push(locale);
+            }
+        }
+
+        Ok(locales)
+    }
+
+    fn load(file: &Path) -> Result<Locale, Error> {
+        Ok(Locale {
+            code: file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            config: Config::load(file)?,
+        })
+    }
+
+    /// The config for reading scripts written in this locale, keeping the rest
+    /// of `config` (e.g. the search path) as it is. Names are translated with
+    /// the section of the glossary for this locale.
+    pub fn apply(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+
+        config.function_keyword = self.config.function_keyword.to_string();
+        config.variable_keyword = self.config.variable_keyword.to_string();
+        config.return_keyword = self.config.return_keyword.to_string();
+        config.none_keyword = self.config.none_keyword.to_string();
+        config.import_keyword = self.config.import_keyword.to_string();
+        config.builtin_names = self.config.builtin_names.clone();
+        config.language = Some(self.language().to_string());
+        config.locale = Some(self.code.to_string());
+
+        config
+    }
+
+    /// Finds the locale out of english and the installed locales that a script
+    /// is written in, by counting the words where a keyword could be that are
+    /// keywords of each locale. Calls to builtins count as well. Returns `None`
+    /// if nothing in the script gives the locale away, e.g. if it only calls
+    /// functions that it imports, or if it matches the locale that `config`
+    /// already reads scripts in as well as any other.
+    pub fn detect(tokens: &[Token], config: &Config) -> Result<Option<Locale>, Error> {
+        let words = keyword_positions(tokens);
+        let calls = calls(tokens);
+
+        let mut scores: Vec<(usize, Locale)> = [Locale::english()]
+            .into_iter()
+            .chain(config.locales.iter().cloned())
+            .map(|locale| (locale.score(&words, &calls), locale))
+            .filter(|(score, _)| *score > 0)
+            .collect();
+        scores.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        match scores.as_slice() {
+            [] => Ok(None),
+            [(best, _), (second, _), ..] if best == second => {
+                let tied: Vec<&Locale> = scores
+                    .iter()
+                    .filter(|(score, _)| score == best)
+                    .map(|(_, locale)| locale)
+                    .collect();
+
+                if tied.iter().any(|locale| locale.is_configured(config)) {
+                    return Ok(None);
+                }
+
+                let tied: Vec<&str> = tied.iter().map(|locale| locale.language()).collect();
+
+                Err(Error::Config(format!(
+                    "Could not tell which locale the script is written in, it matches {} equally well",
+                    tied.join(" and ")
+                )))
+            }
+            [(_, best), ..] => Ok(Some(best.clone())),
+        }
+    }
+
+    /// Whether `config` already reads scripts the way this locale does
+    fn is_configured(&self, config: &Config) -> bool {
+        let locale = &self.config;
+
+        locale.function_keyword == config.function_keyword
+            && locale.variable_keyword == config.variable_keyword
+            && locale.return_keyword == config.return_keyword
+            && locale.none_keyword == config.none_keyword
+            && locale.import_keyword == config.import_keyword
+            && locale.builtin_names == config.builtin_names
+    }
+
+    fn score(&self, words: &[&str], calls: &[&str]) -> usize {
+        let config = &self.config;
+        let keywords = [
+            &config.function_keyword,
+            &config.variable_keyword,
+            &config.return_keyword,
+            &config.none_keyword,
+            &config.import_keyword,
+        ];
+
+        // English builtin names work in every locale, so they only count
+        // towards english
+        let builtins: Vec<&str> = match config.builtin_names.is_empty() {
+            true => BUILTIN_CHARACTERS.iter().map(|(name, _)| *name).collect(),
+            false => config
+                .builtin_names
+                .iter()
+                .map(|(_, localized)| localized.as_str())
+                .collect(),
+        };
+
+        words
+            .iter()
+            .filter(|word| keywords.iter().any(|keyword| keyword == *word))
+            .count()
+            + calls.iter().filter(|call| builtins.contains(call)).count()
+    }
+}
+
+/// The identifiers that are where a keyword could be: at the start of a
+/// statement, where a value is expected (e.g. `none` or an anonymous function)
+/// or after another word (e.g. the value after a return keyword)
+fn keyword_positions(tokens: &[Token]) -> Vec<&str> {
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            let previous = index.checked_sub(1).map(|index| &tokens[index].token_type);
+
+            matches!(
+                previous,
+                None | Some(
+                    TokenTypes::Semi
+                        | TokenTypes::OpenCurly
+                        | TokenTypes::CloseCurly
+                        | TokenTypes::Comment { .. }
+                        | TokenTypes::Equals
+                        | TokenTypes::EqualEqual
+                        | TokenTypes::BangEqual
+                        | TokenTypes::OpenParen
+                        | TokenTypes::OpenBracket
+                        | TokenTypes::Comma
+                        | TokenTypes::Colon
+                        | TokenTypes::Identifier { .. }
+                )
+            )
+        })
+        .filter_map(|(_, token)| match &token.token_type {
+            TokenTypes::Identifier { value } => Some(value.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The names of the functions that a script calls
+fn calls(tokens: &[Token]) -> Vec<&str> {
+    tokens
+        .windows(2)
+        .filter_map(|pair| match (&pair[0].token_type, &pair[1].token_type) {
+            (TokenTypes::Identifier { value }, TokenTypes::OpenParen) => Some(value.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate::Tokenizer;
+
+    fn locale(code: &str, language: &str, keywords: [&str; 5], print: &str) -> Locale {
+        let mut config = Config::default();
+        let [function, variable, return_keyword, none, import] = keywords.map(String::from);
+
+        config.function_keyword = function;
+        config.variable_keyword = variable;
+        config.return_keyword = return_keyword;
+        config.none_keyword = none;
+        config.import_keyword = import;
+        config.builtin_names = vec![(String::from("print"), String::from(print))];
+        config.language = Some(String::from(language));
+
+        Locale {
+            code: String::from(code),
+            config,
+        }
+    }
+
+    fn installed() -> Config {
+        let mut config = Config::default();
+        config.locales = vec![
+            locale(
+                "fi",
+                "Finnish",
+                ["funktio", "olkoon", "palauta", "tyhjä", "tuo"],
+                "tulosta",
+            ),
+            locale(
+                "de",
+                "German",
+                ["funktion", "sei", "zurück", "nichts", "importiere"],
+                "drucke",
+            ),
+        ];
+        config
+    }
+
+    fn detect(source: &str, config: &Config) -> Result<Option<String>, Error> {
+        let tokens = Tokenizer::from_string(source.to_string()).tokenize();
+
+        Ok(Locale::detect(&tokens, config)?.map(|locale| locale.code))
+    }
+
+    #[test]
+    fn scripts_are_detected_in_each_locale() {
+        let config = installed();
+
+        assert_eq!(
+            detect(
+                "funktio kaksi(x) {\n    palauta x * 2;\n};\nolkoon a = tyhjä;\ntulosta(kaksi(1));",
+                &config
+            )
+            .unwrap()
+            .as_deref(),
+            Some("fi")
+        );
+        assert_eq!(
+            detect(
+                "funktion doppelt(x) {\n    zurück x * 2;\n};\nsei a = nichts;\ndrucke(doppelt(1));",
+                &config
+            )
+            .unwrap()
+            .as_deref(),
+            Some("de")
+        );
+        assert_eq!(
+            detect("let a = none;\nprint(a);", &config)
+                .unwrap()
+                .as_deref(),
+            Some("en")
+        );
+    }
+
+    #[test]
+    fn scripts_without_keywords_are_read_as_configured() {
+        assert_eq!(detect("a = f(1);\ng(a);", &installed()).unwrap(), None);
+    }
+
+    #[test]
+    fn ties_with_the_configured_locale_are_read_as_configured() {
+        let mut config = installed();
+        config.locales[0].config.none_keyword = String::from("none");
+
+        assert_eq!(detect("a = none;", &config).unwrap(), None);
+    }
+
+    #[test]
+    fn ties_between_other_locales_are_reported() {
+        let mut config = installed();
+        config.locales[1].config.none_keyword = String::from("tyhjä");
+
+        match detect("a = tyhjä;", &config) {
+            Err(Error::Config(message)) => assert_eq!(
+                message,
+                "Could not tell which locale the script is written in, it matches Finnish and German equally well"
+            ),
+            result => panic!("expected the tie to be reported, got {:?}", result.ok()),
+        }
+    }
+}
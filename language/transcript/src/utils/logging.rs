@@ -0,0 +1,109 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use colored::{Color, Colorize};
+use log::{error, warn};
+
+use crate::{
+    file::{SourceMap, Span},
+    translate::Token,
+};
+
+/// Another file that diagnostics about a file are shown in, e.g. the unpacked
+/// version of a packed script
+struct View {
+    map: SourceMap,
+    lines: Vec<String>,
+}
+
+thread_local! {
+    static VIEWS: RefCell<HashMap<PathBuf, View>> = RefCell::new(HashMap::new());
+}
+
+/// Shows diagnostics about tokens from `generated` in `original` instead, at
+/// the position that `map` gives for each token
+pub fn show_in(generated: &Path, map: SourceMap, original: &str) {
+    let view = View {
+        map,
+        lines: original.split('\n').map(str::to_string).collect(),
+    };
+
+    VIEWS.with(|views| views.borrow_mut().insert(generated.to_path_buf(), view));
+}
+
+/// Where a token should be shown, along with the line it is on
+fn locate(token: &Token) -> (Span, String) {
+    let shown = token.path.as_ref().and_then(|path| {
+        VIEWS.with(|views| {
+            let views = views.borrow();
+            let view = views.get(path.as_ref())?;
+            let span = view.map.original(Span::from(token))?;
+
+            Some((span, view.lines.get(span.line - 1)?.to_string()))
+        })
+    });
+
+    shown.unwrap_or_else(|| (Span::from(token), token.line_contents.to_string()))
+}
+
+#[allow(dead_code)]
+enum LogType {
+    Info,
+    Warning,
+    Error,
+}
+
+fn format_token(token: &Token, log_type: LogType) -> String {
+    let highlight_symbol = match log_type {
+        LogType::Info => "﹉",
+        LogType::Warning => "~",
+        LogType::Error => "‾",
+    };
+
+    let color = match log_type {
+        LogType::Info => Color::Green,
+        LogType::Warning => Color::Yellow,
+        LogType::Error => Color::Red,
+    };
+
+    let (span, line_contents) = locate(token);
+
+    format!(
+        "{} | {}\n{}   {}{}",
+        span.line,
+        line_contents,
+        " ".repeat(span.line.to_string().len()),
+        " ".repeat(span.start),
+        highlight_symbol.repeat(span.end - span.start).color(color)
+    )
+}
+
+/// Logs a warning along with the line that caused it
+pub fn warn_token(token: &Token, message: &str) {
+    warn!("{}\n{}\n", message, format_token(token, LogType::Warning));
+}
+
+/// Logs an error along with the line that caused it
+pub fn error_token(token: &Token, message: &str) {
+    error!("{}\n{}\n", message, format_token(token, LogType::Error));
+}
+
+/// Logs an error along with the line that caused it, followed by notes on how
+/// to fix it
+pub fn error_token_with_help(token: &Token, message: &str, help: &[String]) {
+    let indent = " ".repeat(locate(token).0.line.to_string().len() + 1);
+    let help: String = help
+        .iter()
+        .map(|note| format!("{}= {} {}\n", indent, "help:".bold(), note))
+        .collect();
+
+    error!(
+        "{}\n{}\n{}",
+        message,
+        format_token(token, LogType::Error),
+        help
+    );
+}
@@ -0,0 +1,11 @@
+mod config;
+mod locale;
+mod logging;
+mod suggest;
+mod validate;
+
+pub use config::*;
+pub use locale::*;
+pub use logging::*;
+pub use suggest::*;
+pub use validate::*;
@@ -0,0 +1,157 @@
+use super::{Config, Locale};
+
+/// The most keywords that are suggested at once
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The number of single character edits needed to turn `a` into `b`, where an
+/// edit inserts, removes or replaces a character, or swaps two neighbouring
+/// characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // distances[i][j] is the distance between the first i characters of `a`
+    // and the first j characters of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+
+            distances[i][j] = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distances[i][j] = distances[i][j].min(distances[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+/// Suggests the keywords that a misspelled word at the start of a statement
+/// could have meant, e.g. "did you mean `toiminto` or English `function`?".
+/// Keywords of the active config are suggested first, then english and every
+/// installed locale, which are named so that the reader knows where they are
+/// from.
+pub fn suggest_keyword(config: &Config, word: &str) -> Option<String> {
+    let english = Locale::english();
+    let languages = [(None, config), (Some(english.language()), &english.config)]
+        .into_iter()
+        .chain(
+            config
+                .locales
+                .iter()
+                .map(|locale| (Some(locale.language()), &locale.config)),
+        );
+
+    let mut suggestions: Vec<(usize, Option<&str>, &str)> = Vec::new();
+
+    for (language, keywords) in languages {
+        for keyword in [
+            &keywords.function_keyword,
+            &keywords.variable_keyword,
+            &keywords.return_keyword,
+            &keywords.import_keyword,
+        ] {
+            if suggestions.iter().any(|(_, _, other)| other == keyword) {
+                continue;
+            }
+
+            // Short words are a single edit away from many others, so longer
+            // words are allowed more edits
+            let distance = edit_distance(word, keyword);
+            if distance <= (keyword.chars().count() / 3).max(1) {
+                suggestions.push((distance, language, keyword));
+            }
+        }
+    }
+
+    // The sort is stable, so keywords that are as close as each other keep the
+    // order of their languages
+    suggestions.sort_by_key(|(distance, _, _)| *distance);
+    suggestions.truncate(MAX_SUGGESTIONS);
+
+    let suggestions: Vec<String> = suggestions
+        .into_iter()
+        .map(|(_, language, keyword)| match language {
+            Some(language) => format!("{} `{}`", language, keyword),
+            None => format!("`{}`", keyword),
+        })
+        .collect();
+
+    let (last, rest) = suggestions.split_last()?;
+    let suggestions = match rest {
+        [] => last.to_string(),
+        rest => format!("{} or {}", rest.join(", "), last),
+    };
+
+    Some(format!("did you mean {}?", suggestions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("", "let"), 3);
+        assert_eq!(edit_distance("return", ""), 6);
+        assert_eq!(edit_distance("return", "return"), 0);
+        assert_eq!(edit_distance("retrun", "return"), 1);
+        assert_eq!(edit_distance("funtcion", "function"), 1);
+        assert_eq!(edit_distance("palauta", "palautus"), 2);
+        // Characters are counted rather than bytes
+        assert_eq!(edit_distance("määrä", "maara"), 3);
+        assert_eq!(edit_distance("tyyppi", "тип"), 6);
+        assert_eq!(edit_distance("ääliö", "äliöä"), 2);
+    }
+
+    #[test]
+    fn keywords_within_the_threshold_are_suggested() {
+        let config = Config::default();
+
+        // "function" allows two edits, but "let" only allows one
+        assert_eq!(
+            suggest_keyword(&config, "fnuctoin").as_deref(),
+            Some("did you mean `function`?")
+        );
+        assert_eq!(
+            suggest_keyword(&config, "lt").as_deref(),
+            Some("did you mean `let`?")
+        );
+        assert_eq!(suggest_keyword(&config, "fnctn"), None);
+        assert_eq!(suggest_keyword(&config, "l"), None);
+    }
+
+    #[test]
+    fn keywords_of_the_config_come_before_english() {
+        let mut config = Config::default();
+        config.function_keyword = String::from("funktio");
+
+        assert_eq!(
+            suggest_keyword(&config, "funktion").as_deref(),
+            Some("did you mean `funktio` or English `function`?")
+        );
+    }
+
+    #[test]
+    fn nothing_is_suggested_when_no_keyword_is_close() {
+        let config = Config::default();
+
+        assert_eq!(suggest_keyword(&config, "print"), None);
+        assert_eq!(suggest_keyword(&config, "x"), None);
+        assert_eq!(suggest_keyword(&config, ""), None);
+    }
+}
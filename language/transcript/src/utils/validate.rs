@@ -0,0 +1,255 @@
+use std::{fmt::Display, path::PathBuf};
+
+use crate::{
+    file::BUILTIN_CHARACTERS,
+    translate::{TokenTypes, Tokenizer},
+};
+
+use super::config::{
+    Config, DEFAULT_FUNCTION_KEYWORD, DEFAULT_IMPORT_KEYWORD, DEFAULT_NONE_KEYWORD,
+    DEFAULT_RETURN_KEYWORD, DEFAULT_VARIABLE_KEYWORD,
+};
+
+/// Something in a config file that would stop scripts from being read the way
+/// their author meant
+#[derive(Clone, Debug)]
+pub struct Problem {
+    /// The config file and line that the problem is on, if it came from a file
+    pub origin: Option<(PathBuf, usize)>,
+    pub message: String,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.origin {
+            Some((file, line)) => write!(f, "{}:{}: {}", file.display(), line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// A name that scripts can use, along with what it means
+struct Name<'a> {
+    spelling: &'a str,
+    /// e.g. "the function keyword" or "the builtin print"
+    meaning: String,
+    section: &'static str,
+    key: &'a str,
+}
+
+impl Config {
+    /// Finds every problem with the keywords and builtin names. Every keyword
+    /// must be read as a single name by the tokenizer, and no two keywords or
+    /// builtins can be spelled the same way.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let problem = |name: &Name, message: String| Problem {
+            origin: self.origin(name.section, name.key),
+            message,
+        };
+
+        let keywords = [
+            ("function", &self.function_keyword, DEFAULT_FUNCTION_KEYWORD),
+            ("variable", &self.variable_keyword, DEFAULT_VARIABLE_KEYWORD),
+            ("return", &self.return_keyword, DEFAULT_RETURN_KEYWORD),
+            ("none", &self.none_keyword, DEFAULT_NONE_KEYWORD),
+            ("import", &self.import_keyword, DEFAULT_IMPORT_KEYWORD),
+        ];
+
+        // Names from the config come first, so that when two names clash the
+        // problem is reported on the line of the one that was set later
+        let mut names: Vec<Name> = Vec::new();
+
+        for (key, spelling, _) in keywords {
+            names.push(Name {
+                spelling,
+                meaning: format!("the {} keyword", key),
+                section: "keywords",
+                key,
+            });
+        }
+
+        for (builtin, localized) in &self.builtin_names {
+            if !BUILTIN_CHARACTERS.iter().any(|(name, _)| name == builtin) {
+                problems.push(Problem {
+                    origin: self.origin("builtins", builtin),
+                    message: format!("There is no builtin called '{}'", builtin),
+                });
+            }
+
+            names.push(Name {
+                spelling: localized,
+                meaning: format!("the builtin {}", builtin),
+                section: "builtins",
+                key: builtin,
+            });
+        }
+
+        // The english keywords and builtin names always work, even when a
+        // locale renames them
+        let defaults: Vec<Name> = keywords
+            .iter()
+            .map(|(key, _, default)| Name {
+                spelling: default,
+                meaning: format!("the english {} keyword", key),
+                section: "keywords",
+                key,
+            })
+            .chain(BUILTIN_CHARACTERS.iter().map(|(builtin, _)| Name {
+                spelling: builtin,
+                meaning: format!("the english builtin {}", builtin),
+                section: "builtins",
+                key: builtin,
+            }))
+            .collect();
+
+        for (index, name) in names.iter().enumerate() {
+            if let Some(message) = tokenizes_as_name(name) {
+                problems.push(problem(name, message));
+                continue;
+            }
+
+            let clash = names[..index]
+                .iter()
+                .chain(defaults.iter().filter(|default| {
+                    // A name can be spelled the same as its own english name
+                    (default.section, default.key) != (name.section, name.key)
+                }))
+                .find(|other| other.spelling == name.spelling);
+
+            if let Some(other) = clash {
+                problems.push(problem(
+                    name,
+                    format!(
+                        "'{}' is used for both {} and {}",
+                        name.spelling, other.meaning, name.meaning
+                    ),
+                ));
+            }
+        }
+
+        problems.sort_by(|a, b| a.origin.cmp(&b.origin));
+        problems
+    }
+}
+
+/// Explains why a name would not be read as one identifier, if it would not
+fn tokenizes_as_name(name: &Name) -> Option<String> {
+    if name.spelling.is_empty() {
+        return Some(format!("{} is empty", capitalize(&name.meaning)));
+    }
+
+    let tokens = Tokenizer::from_string(name.spelling.to_string()).tokenize();
+
+    match tokens.as_slice() {
+        [token] if token.token_type.is_identifier() => None,
+        [token] => Some(format!(
+            "'{}' cannot be used as {}, because it is read as {}",
+            name.spelling,
+            name.meaning,
+            match &token.token_type {
+                TokenTypes::Number { .. } => "a number",
+                TokenTypes::String { .. } => "a string",
+                TokenTypes::Comment { .. } => "a comment",
+                _ => "a symbol",
+            }
+        )),
+        tokens => Some(format!(
+            "'{}' cannot be used as {}, because it is read as {} separate tokens",
+            name.spelling,
+            name.meaning,
+            tokens.len()
+        )),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path};
+
+    use crate::Error;
+
+    use super::*;
+
+    /// Loads a config file with `contents`, returning its problems
+    fn problems(test: &str, contents: &str) -> (PathBuf, Vec<Problem>) {
+        let file = env::temp_dir().join(format!("transcript-{}-{}.ini", test, std::process::id()));
+        fs::write(&file, contents).unwrap();
+
+        let problems = match Config::load(&file) {
+            Err(Error::InvalidConfig(problems)) => problems,
+            Err(error) => panic!("{} failed with {}", test, error),
+            Ok(_) => Vec::new(),
+        };
+
+        fs::remove_file(&file).unwrap();
+        (file, problems)
+    }
+
+    fn origin(file: &Path, line: usize) -> Option<(PathBuf, usize)> {
+        Some((file.to_path_buf(), line))
+    }
+
+    #[test]
+    fn keywords_must_be_read_as_one_name() {
+        let (file, problems) = problems(
+            "keywords",
+            "[keywords]\nfunction = tee funktio\nvariable = 42\nreturn = \n",
+        );
+        let messages: Vec<_> = problems.iter().map(|p| p.message.as_str()).collect();
+
+        assert_eq!(
+            messages,
+            [
+                "'tee funktio' cannot be used as the function keyword, because it is read as 2 separate tokens",
+                "'42' cannot be used as the variable keyword, because it is read as a number",
+                "The return keyword is empty",
+            ]
+        );
+        assert_eq!(problems[0].origin, origin(&file, 2));
+        assert_eq!(problems[1].origin, origin(&file, 3));
+        assert_eq!(problems[2].origin, origin(&file, 4));
+    }
+
+    #[test]
+    fn names_cannot_be_spelled_the_same() {
+        let (file, problems) = problems(
+            "duplicates",
+            "[keywords]\nfunction = tee\nvariable = olkoon\n\n[builtins]\nprint = tee\nlen = let\n",
+        );
+
+        assert_eq!(problems.len(), 2);
+        assert_eq!(
+            problems[0].message,
+            "'tee' is used for both the function keyword and the builtin print"
+        );
+        assert_eq!(problems[0].origin, origin(&file, 6));
+        assert_eq!(
+            problems[1].message,
+            "'let' is used for both the english variable keyword and the builtin len"
+        );
+        assert_eq!(
+            problems[1].to_string(),
+            format!("{}:7: {}", file.display(), problems[1].message)
+        );
+    }
+
+    #[test]
+    fn names_can_be_spelled_like_their_own_english_name() {
+        let (_, problems) = problems(
+            "english",
+            "[keywords]\nfunction = function\n\n[builtins]\nprint = print\n",
+        );
+
+        assert!(problems.is_empty());
+    }
+}
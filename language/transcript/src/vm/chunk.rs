@@ -0,0 +1,125 @@
+use std::rc::Rc;
+
+use crate::{executor::Value, translate::Token};
+
+/// A single bytecode instruction. Operands are stored inline so that an
+/// instruction fits in six bytes and the whole chunk stays in cache.
+/// Instructions that can fail take the index of a token in the chunk to report
+/// the error against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    /// Push the constant at the given index onto the stack
+    Constant(u16),
+    /// Push `Value::Option(None)` onto the stack
+    None,
+    /// Discard the top of the stack
+    Pop,
+
+    /// Push the value of a local slot in the current call frame
+    GetLocal(u8),
+    /// Pop the top of the stack into a local slot in the current call frame
+    SetLocal(u8),
+    /// Push the value of a global
+    GetGlobal(u16, u16),
+    /// Pop the top of the stack into an existing global
+    SetGlobal(u16, u16),
+    /// Pop the top of the stack into a global, creating it if necessary
+    DefineGlobal(u16),
+    /// Push the value of the builtin in the given slot of the builtins
+    GetBuiltin(u16),
+    /// Pop the top of the stack into the builtin in the given slot
+    SetBuiltin(u16),
+    /// Push the value of a variable captured from an enclosing function
+    GetUpvalue(u8),
+    /// Pop the top of the stack into a variable captured from an enclosing
+    /// function
+    SetUpvalue(u8),
+
+    /// Create a closure of the function at the given index, capturing the
+    /// variables that it uses from the current call frame
+    Closure(u16),
+
+    Add(u16),
+    Subtract(u16),
+    Multiply(u16),
+    Divide(u16),
+    Negate(u16),
+    Equal,
+    NotEqual,
+
+    /// Create a list out of the given number of values on the top of the stack
+    List(u16),
+    /// Create a map out of the given number of key and value pairs on the top
+    /// of the stack
+    Map(u16),
+    /// Pop an index and a list (or a key and a map) and push the item at that
+    /// index. The operand is the token of the index expression, used to report
+    /// errors
+    Index(u16),
+    /// Pop a value, an index and a list (or a key and a map) and set the item
+    /// at that index
+    SetIndex(u16),
+    /// Pop the end and start bounds and a list and push a copy of that part
+    /// of the list. Missing bounds are none.
+    Slice(u16),
+
+    /// Unwrap the option on the top of the stack, returning none from the
+    /// current function if it is empty
+    Propagate(u16),
+
+    /// Call the value below the given number of arguments on the stack
+    Call(u8, u16),
+    /// Return the top of the stack to the caller
+    Return,
+}
+
+#[derive(Default)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    pub constants: Vec<Value>,
+    /// The functions defined inside of this chunk
+    pub functions: Vec<Rc<Function>>,
+    /// Tokens that instructions can report runtime errors against
+    pub tokens: Vec<Token>,
+}
+
+impl Chunk {
+    pub fn push(&mut self, instruction: Instruction) {
+        self.code.push(instruction);
+    }
+
+    pub fn add_constant(&mut self, value: Value) -> u16 {
+        self.constants.push(value);
+        u16::try_from(self.constants.len() - 1).expect("Too many constants in one function!")
+    }
+
+    pub fn add_token(&mut self, token: Token) -> u16 {
+        self.tokens.push(token);
+        u16::try_from(self.tokens.len() - 1).expect("Too many tokens in one function!")
+    }
+
+    pub fn add_function(&mut self, function: Rc<Function>) -> u16 {
+        self.functions.push(function);
+        u16::try_from(self.functions.len() - 1).expect("Too many functions in one function!")
+    }
+}
+
+/// A function that has been compiled to bytecode.
+pub struct Function {
+    pub name: String,
+    pub args: Vec<String>,
+    /// The number of local slots, including the arguments
+    pub locals: usize,
+    /// The variables that closures of this function capture when created
+    pub captures: Vec<Capture>,
+    pub chunk: Chunk,
+}
+
+/// Where a closure gets a captured variable from when it is created
+#[derive(Debug, Clone, Copy)]
+pub struct Capture {
+    /// If true, `index` is a local slot of the enclosing function. Otherwise
+    /// it is one of the enclosing function's own captures.
+    pub local: bool,
+    pub index: u8,
+}
@@ -0,0 +1,39 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::executor::{Environment, Value};
+
+use super::Function;
+
+/// A function along with the variables it has captured from the functions
+/// around it.
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    /// The globals of the program the function was compiled in. Functions
+    /// imported from another module keep using that module's globals.
+    pub globals: Rc<Globals>,
+}
+
+/// The global variables of a running program, indexed the same way as
+/// `Program::globals`
+pub struct Globals {
+    pub names: Vec<String>,
+    pub values: RefCell<Vec<Option<Value>>>,
+    /// The environment around the globals, which holds the builtins
+    pub builtins: Rc<RefCell<Environment>>,
+}
+
+impl Globals {
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let index = self.names.iter().position(|existing| existing == name)?;
+        self.values.borrow()[index].clone()
+    }
+}
+
+/// A captured variable. While the function that defines the variable is still
+/// running, the variable lives on the stack and is shared with the closure.
+/// Once that function returns, the value is moved into the upvalue itself.
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
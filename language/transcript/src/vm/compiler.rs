@@ -0,0 +1,483 @@
+use std::rc::Rc;
+
+use crate::{
+    executor::{warn_about_text_tokens, Value},
+    file::{
+        FUNCTION_DEFINITION_CHARACTER, IMPORT_CHARACTER, NONE_CHARACTER, RETURN_CHARACTER,
+        VARIABLE_DEFINITION_CHARACTER,
+    },
+    translate::{EqualitySymbol, FactorSymbol, TermSymbol, Token, TokenTypes, Variable, AST},
+};
+
+use super::{Capture, Chunk, Function, Instruction};
+
+/// The output of the compiler. Globals are referenced by index in the
+/// bytecode, `globals` maps those indexes back to their names so the machine
+/// can pick up anything that was defined in the `Environment` before the
+/// script ran (e.g. by an earlier script). Builtins are referenced by their
+/// slot in the environment around the globals.
+pub struct Program {
+    pub main: Rc<Function>,
+    pub globals: Vec<String>,
+}
+
+#[derive(Default)]
+struct FunctionScope {
+    /// The number of local slots used by the function so far
+    locals: usize,
+    captures: Vec<Capture>,
+    chunk: Chunk,
+}
+
+enum Location {
+    Local(u8),
+    Upvalue(u8),
+    Global(u16),
+    /// A slot of the builtins around the script. These are found by slot
+    /// rather than by name, since the script can define globals with the same
+    /// name
+    Builtin(u16),
+}
+
+struct Compiler {
+    globals: Vec<String>,
+    /// The functions that are currently being compiled. The first scope is the
+    /// script itself, whose definitions are globals.
+    scopes: Vec<FunctionScope>,
+}
+
+/// Compiles an AST that has already been through `translate::resolve`. Local
+/// variables use the slots that were assigned by the resolver.
+pub fn compile(ast: &AST) -> Program {
+    let mut compiler = Compiler {
+        globals: Vec::new(),
+        scopes: vec![FunctionScope::default()],
+    };
+
+    compiler.statement(ast);
+    compiler.emit(Instruction::None);
+    compiler.emit(Instruction::Return);
+
+    let scope = compiler.scopes.pop().unwrap();
+
+    Program {
+        main: Rc::new(Function {
+            name: String::from("<script>"),
+            args: Vec::new(),
+            locals: 0,
+            captures: Vec::new(),
+            chunk: scope.chunk,
+        }),
+        globals: compiler.globals,
+    }
+}
+
+fn param_name(token: &Token) -> String {
+    match &token.token_type {
+        TokenTypes::Identifier { value } => value.to_string(),
+        _ => panic!("Function parameters must be identifiers!"),
+    }
+}
+
+impl Compiler {
+    fn emit(&mut self, instruction: Instruction) {
+        self.scopes.last_mut().unwrap().chunk.push(instruction);
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        let constant = self.scopes.last_mut().unwrap().chunk.add_constant(value);
+        self.emit(Instruction::Constant(constant));
+    }
+
+    fn token(&mut self, token: &Token) -> u16 {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .chunk
+            .add_token(token.clone())
+    }
+
+    fn global(&mut self, name: &str) -> u16 {
+        let index = match self.globals.iter().position(|global| global == name) {
+            Some(index) => index,
+            None => {
+                self.globals.push(name.to_string());
+                self.globals.len() - 1
+            }
+        };
+
+        u16::try_from(index).expect("Too many globals in one script!")
+    }
+
+    fn locate(&mut self, variable: &Variable) -> Location {
+        let binding = variable
+            .binding
+            .expect("Variables must be resolved before they are compiled");
+
+        let innermost = self.scopes.len() - 1;
+
+        // The script scope is always the outermost scope, apart from the
+        // builtins around it
+        if binding.depth > innermost {
+            return Location::Builtin(u16::try_from(binding.slot).expect("Too many builtins!"));
+        }
+
+        if binding.depth == innermost {
+            return Location::Global(self.global(&variable.name));
+        }
+
+        if binding.depth == 0 {
+            return Location::Local(self.local(innermost, binding.slot));
+        }
+
+        Location::Upvalue(self.capture(innermost, binding.depth, binding.slot))
+    }
+
+    /// Makes sure that the function at `scope` has room for the given slot
+    fn local(&mut self, scope: usize, slot: usize) -> u8 {
+        let scope = &mut self.scopes[scope];
+        scope.locals = scope.locals.max(slot + 1);
+
+        u8::try_from(slot).expect("Too many local variables in one function!")
+    }
+
+    /// Captures a variable that is `depth` functions above the function at
+    /// `scope`. Every function in between captures it as well, so that it can
+    /// be passed down when the closures are created.
+    fn capture(&mut self, scope: usize, depth: usize, slot: usize) -> u8 {
+        let capture = if depth == 1 {
+            Capture {
+                local: true,
+                index: self.local(scope - 1, slot),
+            }
+        } else {
+            Capture {
+                local: false,
+                index: self.capture(scope - 1, depth - 1, slot),
+            }
+        };
+
+        let captures = &mut self.scopes[scope].captures;
+        let index = match captures
+            .iter()
+            .position(|existing| existing.local == capture.local && existing.index == capture.index)
+        {
+            Some(index) => index,
+            None => {
+                captures.push(capture);
+                captures.len() - 1
+            }
+        };
+
+        u8::try_from(index).expect("Too many captured variables in one function!")
+    }
+
+    fn get(&mut self, variable: &Variable) {
+        match self.locate(variable) {
+            Location::Local(slot) => self.emit(Instruction::GetLocal(slot)),
+            Location::Upvalue(upvalue) => self.emit(Instruction::GetUpvalue(upvalue)),
+            Location::Global(global) => {
+                let token = self.token(&variable.token);
+                self.emit(Instruction::GetGlobal(global, token))
+            }
+            Location::Builtin(slot) => self.emit(Instruction::GetBuiltin(slot)),
+        }
+    }
+
+    /// Pops the top of the stack into an existing variable
+    fn set(&mut self, variable: &Variable) {
+        match self.locate(variable) {
+            Location::Local(slot) => self.emit(Instruction::SetLocal(slot)),
+            Location::Upvalue(upvalue) => self.emit(Instruction::SetUpvalue(upvalue)),
+            Location::Global(global) => {
+                let token = self.token(&variable.token);
+                self.emit(Instruction::SetGlobal(global, token))
+            }
+            Location::Builtin(slot) => self.emit(Instruction::SetBuiltin(slot)),
+        }
+    }
+
+    /// Pops the top of the stack into a new variable in the current scope
+    fn define(&mut self, variable: &Variable) {
+        match self.locate(variable) {
+            Location::Global(global) => self.emit(Instruction::DefineGlobal(global)),
+            _ => self.set(variable),
+        }
+    }
+
+    /// Compiles a function and emits the instruction that creates a closure of
+    /// it
+    fn function(&mut self, name: &str, params: &[Token], body: &AST) {
+        let args: Vec<String> = params.iter().map(param_name).collect();
+
+        self.scopes.push(FunctionScope {
+            locals: args.len(),
+            captures: Vec::new(),
+            chunk: Chunk::default(),
+        });
+
+        // Functions without a return statement return none
+        self.statement(body);
+        self.emit(Instruction::None);
+        self.emit(Instruction::Return);
+
+        let scope = self.scopes.pop().unwrap();
+
+        let function = Rc::new(Function {
+            name: name.to_string(),
+            args,
+            locals: scope.locals,
+            captures: scope.captures,
+            chunk: scope.chunk,
+        });
+
+        let index = self.scopes.last_mut().unwrap().chunk.add_function(function);
+        self.emit(Instruction::Closure(index));
+    }
+
+    /// Compiles a statement, leaving the stack as it was before
+    fn statement(&mut self, code: &AST) {
+        match code {
+            AST::Block { statements } => {
+                for statement in statements {
+                    self.statement(statement);
+                }
+            }
+            AST::VariableDefinition {
+                variable,
+                value,
+                keyword_token,
+            } => {
+                if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                    if value != VARIABLE_DEFINITION_CHARACTER {
+                        warn_about_text_tokens(keyword_token);
+                    }
+                }
+
+                self.expression(value);
+                self.define(variable);
+            }
+            AST::FunctionDefinition {
+                variable,
+                params,
+                body,
+                keyword_token,
+            } => {
+                if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                    if value != FUNCTION_DEFINITION_CHARACTER {
+                        warn_about_text_tokens(keyword_token);
+                    }
+                }
+
+                self.function(&variable.name, params, body);
+                self.define(variable);
+            }
+            AST::Assignment { variable, value } => {
+                self.expression(value);
+                self.set(variable);
+            }
+            AST::IndexAssignment { target, value } => {
+                let token = match target.as_ref() {
+                    AST::Index {
+                        value,
+                        index,
+                        token,
+                    } => {
+                        self.expression(value);
+                        self.expression(index);
+                        token
+                    }
+                    AST::Field { value, name, token } => {
+                        self.expression(value);
+                        self.emit_constant(Value::String(name.to_string()));
+                        token
+                    }
+                    _ => unreachable!("The parser only assigns to indexes and fields"),
+                };
+
+                self.expression(value);
+
+                let token = self.token(token);
+                self.emit(Instruction::SetIndex(token));
+            }
+            AST::Return {
+                value,
+                keyword_token,
+            } => {
+                if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                    if value != RETURN_CHARACTER {
+                        warn_about_text_tokens(keyword_token);
+                    }
+                }
+
+                match value {
+                    Some(value) => self.expression(value),
+                    None => self.emit(Instruction::None),
+                }
+
+                self.emit(Instruction::Return);
+            }
+            AST::Comment { value: _ } => (),
+            _ => {
+                self.expression(code);
+                self.emit(Instruction::Pop);
+            }
+        }
+    }
+
+    /// Compiles an expression, leaving its value on the top of the stack
+    fn expression(&mut self, code: &AST) {
+        match code {
+            AST::Function {
+                params,
+                body,
+                keyword_token,
+            } => {
+                if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                    if value != FUNCTION_DEFINITION_CHARACTER {
+                        warn_about_text_tokens(keyword_token);
+                    }
+                }
+
+                self.function("<anonymous>", params, body);
+            }
+            AST::FunctionCall {
+                function,
+                args,
+                token,
+            } => {
+                self.expression(function);
+
+                for arg in args {
+                    self.expression(arg);
+                }
+
+                let count = u8::try_from(args.len()).expect("Too many arguments in one call!");
+                let token = self.token(token);
+                self.emit(Instruction::Call(count, token));
+            }
+            AST::List { items } => {
+                for item in items {
+                    self.expression(item);
+                }
+
+                let count = u16::try_from(items.len()).expect("Too many items in one list!");
+                self.emit(Instruction::List(count));
+            }
+            AST::Map { entries } => {
+                for (key, value) in entries {
+                    self.emit_constant(Value::String(key.to_string()));
+                    self.expression(value);
+                }
+
+                let count = u16::try_from(entries.len()).expect("Too many entries in one map!");
+                self.emit(Instruction::Map(count));
+            }
+            AST::Field { value, name, token } => {
+                self.expression(value);
+                self.emit_constant(Value::String(name.to_string()));
+
+                let token = self.token(token);
+                self.emit(Instruction::Index(token));
+            }
+            AST::Index {
+                value,
+                index,
+                token,
+            } => {
+                self.expression(value);
+                self.expression(index);
+
+                let token = self.token(token);
+                self.emit(Instruction::Index(token));
+            }
+            AST::Slice {
+                value,
+                start,
+                end,
+                token,
+            } => {
+                self.expression(value);
+
+                for bound in [start, end] {
+                    match bound {
+                        Some(bound) => self.expression(bound),
+                        None => self.emit(Instruction::None),
+                    }
+                }
+
+                let token = self.token(token);
+                self.emit(Instruction::Slice(token));
+            }
+            AST::Propagate { value, token } => {
+                self.expression(value);
+
+                let token = self.token(token);
+                self.emit(Instruction::Propagate(token));
+            }
+            AST::Equality(left, op, right) => {
+                self.expression(left);
+                self.expression(right);
+                self.emit(match op {
+                    EqualitySymbol::Equal => Instruction::Equal,
+                    EqualitySymbol::NotEqual => Instruction::NotEqual,
+                });
+            }
+            AST::Term(left, op, right, token) => {
+                self.expression(left);
+                self.expression(right);
+
+                let token = self.token(token);
+                self.emit(match op {
+                    TermSymbol::Add => Instruction::Add(token),
+                    TermSymbol::Sub => Instruction::Subtract(token),
+                });
+            }
+            AST::Factor(left, op, right, token) => {
+                self.expression(left);
+                self.expression(right);
+
+                let token = self.token(token);
+                self.emit(match op {
+                    FactorSymbol::Mul => Instruction::Multiply(token),
+                    FactorSymbol::Div => Instruction::Divide(token),
+                });
+            }
+            AST::Unary(op, value, token) => {
+                self.expression(value);
+
+                if *op == TermSymbol::Sub {
+                    let token = self.token(token);
+                    self.emit(Instruction::Negate(token));
+                }
+            }
+            AST::Group(value) => self.expression(value),
+            AST::Number(number) => self.emit_constant(Value::Number(*number)),
+            AST::String(string) => self.emit_constant(Value::String(string.to_string())),
+            AST::Identifier(variable) => self.get(variable),
+            AST::Import {
+                variable,
+                keyword_token,
+                ..
+            } => {
+                if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                    if value != IMPORT_CHARACTER {
+                        warn_about_text_tokens(keyword_token);
+                    }
+                }
+
+                self.get(variable);
+            }
+            AST::None { keyword_token } => {
+                if let TokenTypes::Identifier { value } = &keyword_token.token_type {
+                    if value != NONE_CHARACTER {
+                        warn_about_text_tokens(keyword_token);
+                    }
+                }
+
+                self.emit(Instruction::None);
+            }
+            _ => unreachable!("The parser only allows statements at the top of a block"),
+        }
+    }
+}
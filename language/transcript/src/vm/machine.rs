@@ -0,0 +1,632 @@
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+use crate::{
+    executor::{
+        arity_error, call_error, enter_caller, get_index, limits, operator_error, propagate_error,
+        runtime_error, save_caller, set_index, slice, type_name, unary_error, undefined, Call,
+        Caller, Environment, Value,
+    },
+    translate::{FactorSymbol, TermSymbol, Token},
+};
+
+use super::{Closure, Globals, Instruction, Program, Upvalue};
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// The index of the first local slot of this frame on the stack. The
+    /// function being called sits directly below it.
+    base: usize,
+    /// Marks the call that created this frame as running, until the frame
+    /// returns. Calls from rust functions have no token, so they leave the
+    /// call to the rust function running
+    _caller: Option<Caller>,
+}
+
+struct Machine {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    /// Upvalues that still point at the stack, sorted by their stack index
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+/// Runs a compiled program. Any globals that the program references but does
+/// not define (e.g. a module's imports) are looked up in `env` before
+/// execution starts, and builtins in the environment around it.
+/// The globals are returned once the program has finished, so that the
+/// definitions of a module can be imported by other scripts.
+pub fn run(program: &Program, env: Rc<RefCell<Environment>>) -> Rc<Globals> {
+    let globals = {
+        let env = env.borrow();
+        Rc::new(Globals {
+            names: program.globals.clone(),
+            // Only the script's own scope, so that a global that has not been
+            // defined yet is not mistaken for the builtin of the same name
+            values: RefCell::new(
+                program
+                    .globals
+                    .iter()
+                    .map(|name| {
+                        let slot = env.names().iter().position(|existing| existing == name)?;
+                        env.get_slot(0, slot)
+                    })
+                    .collect(),
+            ),
+            builtins: env
+                .enclosing()
+                .expect("The globals are inside of the builtins"),
+        })
+    };
+
+    let main = Rc::new(Closure {
+        function: program.main.clone(),
+        upvalues: Vec::new(),
+        globals: globals.clone(),
+    });
+
+    // The frames are dropped in any order if the script fails, so the call
+    // that was running before is put back afterwards
+    let _caller = save_caller();
+    let mut machine = Machine {
+        stack: vec![Value::CompiledFunction(main.clone())],
+        frames: vec![Frame {
+            closure: main,
+            ip: 0,
+            base: 1,
+            _caller: None,
+        }],
+        open_upvalues: Vec::new(),
+    };
+
+    machine.run(0);
+
+    globals
+}
+
+/// Rust functions that call back into the script (e.g. `map`) run the function
+/// on the same machine, so that closures can still reach variables on the
+/// stack
+impl Call for Machine {
+    fn call(&mut self, function: &Value, args: Vec<Value>) -> Value {
+        let depth = self.frames.len();
+        let count = args.len();
+
+        self.stack.push(function.clone());
+        self.stack.extend(args);
+        self.invoke(count, None);
+
+        if self.frames.len() > depth {
+            self.run(depth)
+        } else {
+            self.pop()
+        }
+    }
+}
+
+impl Machine {
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("Stack underflow!")
+    }
+
+    fn pop_numbers(&mut self, operator: impl Display, token: u16) -> (f32, f32) {
+        let right = self.pop();
+        let left = self.pop();
+
+        match (left, right) {
+            (Value::Number(l), Value::Number(r)) => (l, r),
+            (left, right) => {
+                runtime_error(self.token(token), &operator_error(operator, &left, &right))
+            }
+        }
+    }
+
+    /// Runs until the number of call frames drops back to `depth`, returning
+    /// the value that the last frame returned
+    fn run(&mut self, depth: usize) -> Value {
+        loop {
+            limits::step();
+
+            let frame = self.frames.last_mut().unwrap();
+            let instruction = frame.closure.function.chunk.code[frame.ip];
+            frame.ip += 1;
+
+            match instruction {
+                Instruction::Constant(constant) => {
+                    let frame = self.frames.last().unwrap();
+                    let value = frame.closure.function.chunk.constants[constant as usize].clone();
+                    self.stack.push(value);
+                }
+                Instruction::None => self.stack.push(Value::Option(None)),
+                Instruction::Pop => {
+                    self.pop();
+                }
+                Instruction::GetLocal(slot) => {
+                    let base = self.frames.last().unwrap().base;
+                    self.stack.push(self.stack[base + slot as usize].clone());
+                }
+                Instruction::SetLocal(slot) => {
+                    let base = self.frames.last().unwrap().base;
+                    let value = self.pop();
+                    self.stack[base + slot as usize] = value;
+                }
+                Instruction::GetGlobal(global, token) => {
+                    let globals = &self.frames.last().unwrap().closure.globals;
+                    let value = match &globals.values.borrow()[global as usize] {
+                        Some(value) => value.clone(),
+                        None => runtime_error(
+                            self.token(token),
+                            &undefined(&globals.names[global as usize]),
+                        ),
+                    };
+                    self.stack.push(value);
+                }
+                Instruction::SetGlobal(global, token) => {
+                    let value = self.pop();
+                    let globals = &self.frames.last().unwrap().closure.globals;
+                    let mut values = globals.values.borrow_mut();
+
+                    if values[global as usize].is_none() {
+                        runtime_error(
+                            self.token(token),
+                            &undefined(&globals.names[global as usize]),
+                        );
+                    }
+
+                    values[global as usize] = Some(value);
+                }
+                Instruction::DefineGlobal(global) => {
+                    let value = self.pop();
+                    let globals = &self.frames.last().unwrap().closure.globals;
+                    globals.values.borrow_mut()[global as usize] = Some(value);
+                }
+                Instruction::GetBuiltin(slot) => {
+                    let globals = &self.frames.last().unwrap().closure.globals;
+                    let value = globals
+                        .builtins
+                        .borrow()
+                        .get_slot(0, slot as usize)
+                        .expect("Builtins are always defined");
+                    self.stack.push(value);
+                }
+                Instruction::SetBuiltin(slot) => {
+                    let value = self.pop();
+                    let globals = &self.frames.last().unwrap().closure.globals;
+                    globals
+                        .builtins
+                        .borrow_mut()
+                        .assign_slot(0, slot as usize, value);
+                }
+                Instruction::GetUpvalue(upvalue) => {
+                    let upvalue =
+                        self.frames.last().unwrap().closure.upvalues[upvalue as usize].clone();
+                    let value = match &*upvalue.borrow() {
+                        Upvalue::Open(index) => self.stack[*index].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.stack.push(value);
+                }
+                Instruction::SetUpvalue(upvalue) => {
+                    let upvalue =
+                        self.frames.last().unwrap().closure.upvalues[upvalue as usize].clone();
+                    let value = self.pop();
+                    match &mut *upvalue.borrow_mut() {
+                        Upvalue::Open(index) => self.stack[*index] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    };
+                }
+                Instruction::Closure(function) => {
+                    let frame = self.frames.last().unwrap();
+                    let function =
+                        frame.closure.function.chunk.functions[function as usize].clone();
+                    let base = frame.base;
+                    let enclosing = frame.closure.clone();
+
+                    let upvalues = function
+                        .captures
+                        .iter()
+                        .map(|capture| {
+                            if capture.local {
+                                self.capture_upvalue(base + capture.index as usize)
+                            } else {
+                                enclosing.upvalues[capture.index as usize].clone()
+                            }
+                        })
+                        .collect();
+
+                    self.stack.push(Value::CompiledFunction(Rc::new(Closure {
+                        function,
+                        upvalues,
+                        globals: enclosing.globals.clone(),
+                    })));
+                }
+                Instruction::Add(token) => {
+                    let right = self.pop();
+                    let left = self.pop();
+
+                    let value = match (left, right) {
+                        (Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+                        (Value::String(l), Value::String(r)) => Value::concatenate(l, &r),
+                        (left, right) => runtime_error(
+                            self.token(token),
+                            &operator_error(TermSymbol::Add, &left, &right),
+                        ),
+                    };
+                    self.stack.push(value);
+                }
+                Instruction::Subtract(token) => {
+                    let (l, r) = self.pop_numbers(TermSymbol::Sub, token);
+                    self.stack.push(Value::Number(l - r));
+                }
+                Instruction::Multiply(token) => {
+                    let (l, r) = self.pop_numbers(FactorSymbol::Mul, token);
+                    self.stack.push(Value::Number(l * r));
+                }
+                Instruction::Divide(token) => {
+                    let (l, r) = self.pop_numbers(FactorSymbol::Div, token);
+                    self.stack.push(Value::Number(l / r));
+                }
+                Instruction::Negate(token) => match self.pop() {
+                    Value::Number(n) => self.stack.push(Value::Number(-n)),
+                    value => {
+                        runtime_error(self.token(token), &unary_error(TermSymbol::Sub, &value))
+                    }
+                },
+                Instruction::List(count) => {
+                    let items = self.stack.split_off(self.stack.len() - count as usize);
+                    self.stack.push(Value::list(items));
+                }
+                Instruction::Map(count) => {
+                    let values = self.stack.split_off(self.stack.len() - count as usize * 2);
+                    let mut entries = Vec::with_capacity(count as usize);
+                    let mut values = values.into_iter();
+
+                    while let (Some(Value::String(key)), Some(value)) =
+                        (values.next(), values.next())
+                    {
+                        entries.push((key, value));
+                    }
+
+                    self.stack.push(Value::map(entries));
+                }
+                Instruction::Index(token) => {
+                    let index = self.pop();
+                    let list = self.pop();
+                    let value = get_index(&list, &index, self.token(token));
+                    self.stack.push(value);
+                }
+                Instruction::SetIndex(token) => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let list = self.pop();
+                    set_index(&list, &index, value, self.token(token));
+                }
+                Instruction::Slice(token) => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    let list = self.pop();
+                    let value = slice(&list, &start, &end, self.token(token));
+                    self.stack.push(value);
+                }
+                Instruction::Equal => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(Value::Boolean(left == right));
+                }
+                Instruction::NotEqual => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(Value::Boolean(left != right));
+                }
+                Instruction::Propagate(token) => match self.pop() {
+                    Value::Option(Some(value)) => self.stack.push(*value),
+                    Value::Option(None) => {
+                        if let Some(result) = self.return_value(Value::Option(None), depth) {
+                            return result;
+                        }
+                    }
+                    value => runtime_error(self.token(token), &propagate_error(&value)),
+                },
+                Instruction::Call(count, token) => self.invoke(count as usize, Some(token)),
+                Instruction::Return => {
+                    let result = self.pop();
+
+                    if let Some(result) = self.return_value(result, depth) {
+                        return result;
+                    }
+                }
+            }
+        }
+    }
+
+    fn token(&self, token: u16) -> &Token {
+        &self.frames.last().unwrap().closure.function.chunk.tokens[token as usize]
+    }
+
+    /// Returns from the current frame. Gives back the result once the number
+    /// of frames has dropped to `depth`.
+    fn return_value(&mut self, result: Value, depth: usize) -> Option<Value> {
+        let frame = self.frames.pop().unwrap();
+
+        // Remove the locals and the function itself, moving any locals that
+        // were captured by closures off the stack first
+        self.close_upvalues(frame.base);
+        self.stack.truncate(frame.base - 1);
+
+        if self.frames.len() == depth {
+            return Some(result);
+        }
+
+        self.stack.push(result);
+        None
+    }
+
+    fn capture_upvalue(&mut self, index: usize) -> Rc<RefCell<Upvalue>> {
+        let existing = self
+            .open_upvalues
+            .iter()
+            .find(|upvalue| matches!(&*upvalue.borrow(), Upvalue::Open(open) if *open == index));
+
+        if let Some(upvalue) = existing {
+            return upvalue.clone();
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(index)));
+        self.open_upvalues.push(upvalue.clone());
+        self.open_upvalues
+            .sort_by_key(|upvalue| match &*upvalue.borrow() {
+                Upvalue::Open(index) => *index,
+                Upvalue::Closed(_) => unreachable!(),
+            });
+
+        upvalue
+    }
+
+    /// Moves every open upvalue at or above `base` off of the stack
+    fn close_upvalues(&mut self, base: usize) {
+        while let Some(upvalue) = self.open_upvalues.last() {
+            let index = match &*upvalue.borrow() {
+                Upvalue::Open(index) => *index,
+                Upvalue::Closed(_) => unreachable!(),
+            };
+
+            if index < base {
+                break;
+            }
+
+            let upvalue = self.open_upvalues.pop().unwrap();
+            *upvalue.borrow_mut() = Upvalue::Closed(self.stack[index].clone());
+        }
+    }
+
+    /// Calls the function below `count` arguments on the stack. `token` is the
+    /// call in the script, which is missing when a rust function calls back
+    /// into the script
+    fn invoke(&mut self, count: usize, token: Option<u16>) {
+        let base = self.stack.len() - count;
+
+        match &self.stack[base - 1] {
+            Value::CompiledFunction(closure) => {
+                let function = &closure.function;
+
+                if function.args.len() != count {
+                    self.call_error(
+                        token,
+                        &arity_error(&function.name, function.args.len(), count),
+                    );
+                }
+
+                let closure = closure.clone();
+                let caller = token.map(|token| enter_caller(self.token(token)));
+
+                // The frame of the script itself is not a call
+                limits::check_depth(self.frames.len());
+
+                for _ in count..closure.function.locals {
+                    self.stack.push(Value::Option(None));
+                }
+
+                self.frames.push(Frame {
+                    closure,
+                    ip: 0,
+                    base,
+                    _caller: caller,
+                });
+            }
+            Value::RustFunction {
+                name, arity, body, ..
+            } => {
+                if !arity.accepts(count) {
+                    self.call_error(token, &arity_error(name, arity, count));
+                }
+
+                let body = body.clone();
+                let call_args = self.stack.split_off(base);
+                self.pop();
+
+                let _caller = token.map(|token| enter_caller(self.token(token)));
+                let result = body(call_args, self);
+                limits::allocate_result(&result);
+
+                self.stack.push(result);
+            }
+            value => {
+                let message = format!("Cannot call {}, it is not a function", type_name(value));
+                self.call_error(token, &message)
+            }
+        }
+    }
+
+    fn call_error(&self, token: Option<u16>, message: &str) -> ! {
+        match token {
+            Some(token) => runtime_error(self.token(token), message),
+            None => call_error(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{fail_both, run_both};
+
+    const MAKE_COUNTER: &str = "
+        function make_counter() {
+            let count = 0;
+            return function () {
+                count = count + 1;
+                return count;
+            };
+        };
+    ";
+
+    #[test]
+    fn functions_see_the_scope_they_are_defined_in() {
+        let source = r#"
+            let name = "global";
+
+            function show_name() {
+                print(name);
+            };
+
+            function shadow_name() {
+                let name = "local";
+                show_name();
+                print(name);
+            };
+
+            shadow_name();
+        "#;
+
+        assert_eq!(run_both(source), "global\nlocal\n");
+    }
+
+    #[test]
+    fn counters_are_independent() {
+        let source = format!(
+            "{}
+            let first = make_counter();
+            let second = make_counter();
+            print(first());
+            print(first());
+            print(second());
+            print(first());
+            print(second());
+            ",
+            MAKE_COUNTER
+        );
+
+        assert_eq!(run_both(&source), "1\n2\n1\n3\n2\n");
+    }
+
+    #[test]
+    fn closures_from_one_call_share_their_variables() {
+        let source = "
+            function make_pair() {
+                let count = 0;
+                let increment = function () {
+                    count = count + 1;
+                };
+                let get = function () {
+                    return count;
+                };
+                return [increment, get];
+            };
+
+            let pair = make_pair();
+            pair[0]();
+            pair[0]();
+            print(pair[1]());
+        ";
+
+        assert_eq!(run_both(source), "2\n");
+    }
+
+    #[test]
+    fn captured_variables_outlive_their_frame() {
+        // Calling other functions reuses the stack slots that the counter's
+        // variable lived in, which must not change the closed over value
+        let source = format!(
+            "{}
+            function clobber(a, b, c) {{
+                let d = a + b + c;
+                return d;
+            }};
+
+            let counter = make_counter();
+            counter();
+            clobber(100, 200, 300);
+            print(counter());
+            ",
+            MAKE_COUNTER
+        );
+
+        assert_eq!(run_both(&source), "2\n");
+    }
+
+    #[test]
+    fn nested_functions_capture_through_every_level() {
+        let source = "
+            function outer(value) {
+                function middle() {
+                    function inner() {
+                        value = value + 1;
+                        return value;
+                    };
+                    return inner;
+                };
+
+                let increment = middle();
+                increment();
+                return function () {
+                    return value;
+                };
+            };
+
+            print(outer(5)());
+            print(outer(10)());
+        ";
+
+        assert_eq!(run_both(source), "6\n11\n");
+    }
+
+    #[test]
+    fn parameters_are_captured() {
+        let source = "
+            function make_adder(amount) {
+                return function (value) {
+                    return value + amount;
+                };
+            };
+
+            let adders = [make_adder(1), make_adder(10)];
+            print(adders[0](5));
+            print(adders[1](5));
+            print(make_adder(100)(5));
+        ";
+
+        assert_eq!(run_both(source), "6\n15\n105\n");
+    }
+
+    #[test]
+    fn globals_named_after_builtins_are_not_the_builtin_before_they_are_defined() {
+        let source = "print(len(\"abc\"));\nfunction len(x) { return 0; };";
+
+        assert_eq!(
+            fail_both(source),
+            (String::from("Undefined variable: len"), Some(1))
+        );
+    }
+
+    #[test]
+    fn builtins_are_found_by_slot_rather_than_by_name() {
+        let source = "
+            function f() {
+                return len(\"ab\");
+            };
+            let len = 5;
+            print(f());
+            print(len);
+        ";
+
+        assert_eq!(run_both(source), "2\n5\n");
+    }
+}
@@ -0,0 +1,13 @@
+//! This module compiles the AST produced by `translate` into bytecode and runs
+//! it on a stack based virtual machine. It is an alternative to the tree
+//! walking interpreter in `executor` for scripts that are run repeatedly.
+
+mod chunk;
+mod closure;
+mod compiler;
+mod machine;
+
+pub use chunk::*;
+pub use closure::*;
+pub use compiler::*;
+pub use machine::*;